@@ -0,0 +1,55 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+// Must match `TransitionSettings` in `src/background/transition.rs`
+struct TransitionSettings {
+    progress: f32,
+    effect: u32,
+    softness: f32,
+    direction: vec2<f32>,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> settings: TransitionSettings;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var from_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var from_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(3) var to_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(4) var to_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(5) var mask_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(6) var mask_sampler: sampler;
+
+// How much of the new background shows on a pixel that starts changing at `start`,
+//  from 0 at the beginning of the transition to 1 at its end
+fn revealed(start: f32) -> f32 {
+    let softness = max(settings.softness, 0.0001);
+    return clamp((settings.progress * (1.0 + softness) - start) / softness, 0.0, 1.0);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let from_color = textureSample(from_texture, from_sampler, in.uv);
+    let to_color = textureSample(to_texture, to_sampler, in.uv);
+    let mask = textureSample(mask_texture, mask_sampler, in.uv);
+    let black = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+
+    // Effects are numbered as in `TransitionMaterial::new`
+    switch settings.effect {
+        case 1u: {
+            // Mask dissolve
+            return mix(from_color, to_color, revealed(mask.r));
+        }
+        case 2u: {
+            // Wipe, where the side the edge leaves from changes first
+            return mix(from_color, to_color, revealed(dot(in.uv - 0.5, settings.direction) + 0.5));
+        }
+        case 3u: {
+            // Fade through black
+            if settings.progress < 0.5 {
+                return mix(from_color, black, settings.progress * 2.0);
+            }
+            return mix(black, to_color, settings.progress * 2.0 - 1.0);
+        }
+        default: {
+            // Crossfade
+            return mix(from_color, to_color, settings.progress);
+        }
+    }
+}
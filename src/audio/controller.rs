@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::asset::{LoadState, LoadedFolder};
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use anyhow::Context;
+
+use crate::compiler::controller::{Controller, ControllerReadyMessage, ScriptSystems, TriggerControllersMessage};
+use crate::VisualNovelState;
+
+/* States */
+#[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
+enum AudioControllerState {
+    #[default]
+    Loading,
+    Idle,
+    Running,
+}
+
+/* Components */
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioChannel {
+    Music,
+    Sound,
+    Voice,
+}
+// Volume of a single playing clip before channel volumes are applied
+#[derive(Component)]
+struct AudioGain(f32);
+#[derive(Component, Debug, Clone)]
+pub struct AudioFade {
+    from: f32,
+    to: f32,
+    timer: Timer,
+    // Fading out, so the clip goes once it's silent
+    despawn: bool,
+}
+impl AudioFade {
+    pub fn new(from: f32, to: f32, seconds: f32) -> Self {
+        Self {
+            from,
+            to,
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            despawn: false,
+        }
+    }
+
+    pub fn out(from: f32, seconds: f32) -> Self {
+        Self { despawn: true, ..Self::new(from, 0., seconds) }
+    }
+
+    // Moves the fade along, returning the clip's gain at that point
+    pub fn tick(&mut self, delta: Duration) -> f32 {
+        self.timer.tick(delta);
+        self.from + (self.to - self.from) * self.timer.fraction()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.timer.is_finished()
+    }
+}
+
+/* Resources */
+#[derive(Resource)]
+struct HandleToAudioFolder(Handle<LoadedFolder>);
+#[derive(Resource, Default)]
+struct AudioClips {
+    music: HashMap<String, Handle<AudioSource>>,
+    sound: HashMap<String, Handle<AudioSource>>,
+    voice: HashMap<String, Handle<AudioSource>>,
+}
+#[derive(Resource)]
+pub struct AudioVolumes {
+    pub master: f32,
+    pub music: f32,
+    pub sound: f32,
+    pub voice: f32,
+}
+impl Default for AudioVolumes {
+    fn default() -> Self {
+        Self { master: 1., music: 1., sound: 1., voice: 1. }
+    }
+}
+impl AudioVolumes {
+    pub fn volume(&self, channel: AudioChannel, gain: f32) -> Volume {
+        let channel_volume = match channel {
+            AudioChannel::Music => self.music,
+            AudioChannel::Sound => self.sound,
+            AudioChannel::Voice => self.voice,
+        };
+        Volume::Linear(self.master * channel_volume * gain)
+    }
+}
+#[derive(Resource, Default)]
+pub struct CurrentMusic(pub Option<String>);
+
+/* Custom types */
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioOperation {
+    PlayMusic(String, f32), // track, crossfade seconds
+    StopMusic(f32), // fade seconds
+    PlaySound(String),
+    PlayVoice(Option<String>), // stops the previous line's voice either way
+}
+
+/* Messages */
+#[derive(Message)]
+pub struct AudioChangeMessage {
+    pub operation: AudioOperation,
+}
+
+pub struct AudioController;
+impl Plugin for AudioController {
+    fn build(&self, app: &mut App) {
+        app.add_message::<AudioChangeMessage>()
+            .init_resource::<AudioVolumes>()
+            .init_resource::<CurrentMusic>()
+            .init_state::<AudioControllerState>()
+            .add_systems(OnEnter(AudioControllerState::Loading), import_audio)
+            .add_systems(Update, setup.run_if(in_state(AudioControllerState::Loading)))
+            .add_systems(Update, wait_trigger.run_if(in_state(AudioControllerState::Idle)))
+            .add_systems(Update, (update_audio, apply_fades, apply_volumes).chain().after(ScriptSystems).run_if(in_state(AudioControllerState::Running)));
+    }
+}
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    folder_handle: Res<HandleToAudioFolder>,
+    mut controller_state: ResMut<NextState<AudioControllerState>>,
+    mut msg_writer: MessageWriter<ControllerReadyMessage>,
+) -> Result<(), BevyError> {
+    let mut clips = AudioClips::default();
+
+    if let Some(state) = asset_server.get_load_state(folder_handle.0.id()) {
+        match state {
+            LoadState::Loaded => {
+                if let Some(loaded_folder) = loaded_folders.get(folder_handle.0.id()) {
+                    for handle in &loaded_folder.handles {
+                        let path = handle.path()
+                            .context("Error retrieving audio path")?
+                            .path();
+
+                        // Clips are sorted into `audio/<channel>/<id>.<ext>`
+                        let channel = match path.iter().nth(1).map(|s| s.to_string_lossy().into_owned()) {
+                            Some(channel) => channel,
+                            None => continue,
+                        };
+                        let filename = path.file_stem()
+                            .context("Audio file has no name")?
+                            .to_string_lossy()
+                            .to_string();
+                        let clips_map = match channel.as_str() {
+                            "music" => &mut clips.music,
+                            "sound" => &mut clips.sound,
+                            "voice" => &mut clips.voice,
+                            other => {
+                                warn!("Ignoring audio file in unknown folder '{}'", other);
+                                continue;
+                            }
+                        };
+                        clips_map.insert(filename, handle.clone().typed());
+                    }
+                }
+
+                commands.insert_resource(clips);
+                controller_state.set(AudioControllerState::Idle);
+                msg_writer.write(ControllerReadyMessage(Controller::Audio));
+            },
+            LoadState::Failed(e) => {
+                return Err(anyhow::anyhow!("Error loading audio assets: {}", e).into());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+fn import_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let loaded_folder = asset_server.load_folder("audio");
+    commands.insert_resource(HandleToAudioFolder(loaded_folder));
+}
+fn wait_trigger(
+    mut msg_reader: MessageReader<TriggerControllersMessage>,
+    mut controller_state: ResMut<NextState<AudioControllerState>>,
+) {
+    if msg_reader.read().count() > 0 {
+        controller_state.set(AudioControllerState::Running);
+    }
+}
+fn fade_out_music(
+    commands: &mut Commands,
+    music_query: &Query<(Entity, &AudioChannel, &AudioGain)>,
+    fade: f32,
+) {
+    for (entity, channel, gain) in music_query.iter() {
+        if *channel != AudioChannel::Music {
+            continue;
+        }
+        if fade > 0. {
+            commands.entity(entity).insert(AudioFade::out(gain.0, fade));
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+fn update_audio(
+    mut commands: Commands,
+    mut audio_change_message: MessageReader<AudioChangeMessage>,
+    clips: Res<AudioClips>,
+    volumes: Res<AudioVolumes>,
+    mut current_music: ResMut<CurrentMusic>,
+    game_state: Res<VisualNovelState>,
+    audio_query: Query<(Entity, &AudioChannel, &AudioGain)>,
+) -> Result<(), BevyError> {
+    for msg in audio_change_message.read() {
+        match &msg.operation {
+            AudioOperation::PlayMusic(music_id, fade) => {
+                let handle = clips.music.get(music_id)
+                    .with_context(|| game_state.diagnostic(format!("Music '{}' does not exist", music_id)))?;
+
+                // Whatever was playing fades out while the new track fades in
+                fade_out_music(&mut commands, &audio_query, *fade);
+                let gain = if *fade > 0. { 0. } else { 1. };
+                let mut track = commands.spawn((
+                    AudioPlayer::new(handle.clone()),
+                    PlaybackSettings::LOOP.with_volume(volumes.volume(AudioChannel::Music, gain)),
+                    AudioChannel::Music,
+                    AudioGain(gain),
+                ));
+                if *fade > 0. {
+                    track.insert(AudioFade::new(0., 1., *fade));
+                }
+                current_music.0 = Some(music_id.clone());
+                println!("[ Playing music '{}' ]", music_id);
+            },
+            AudioOperation::StopMusic(fade) => {
+                fade_out_music(&mut commands, &audio_query, *fade);
+                current_music.0 = None;
+                println!("[ Stopping music ]");
+            },
+            AudioOperation::PlaySound(sound_id) => {
+                let handle = clips.sound.get(sound_id)
+                    .with_context(|| game_state.diagnostic(format!("Sound '{}' does not exist", sound_id)))?;
+                commands.spawn((
+                    AudioPlayer::new(handle.clone()),
+                    PlaybackSettings::DESPAWN.with_volume(volumes.volume(AudioChannel::Sound, 1.)),
+                    AudioChannel::Sound,
+                    AudioGain(1.),
+                ));
+            },
+            AudioOperation::PlayVoice(voice_id) => {
+                for (entity, channel, _) in audio_query.iter() {
+                    if *channel == AudioChannel::Voice {
+                        commands.entity(entity).despawn();
+                    }
+                }
+                if let Some(voice_id) = voice_id {
+                    let handle = clips.voice.get(voice_id)
+                        .with_context(|| game_state.diagnostic(format!("Voice clip '{}' does not exist", voice_id)))?;
+                    commands.spawn((
+                        AudioPlayer::new(handle.clone()),
+                        PlaybackSettings::DESPAWN.with_volume(volumes.volume(AudioChannel::Voice, 1.)),
+                        AudioChannel::Voice,
+                        AudioGain(1.),
+                    ));
+                }
+            },
+        }
+    }
+    Ok(())
+}
+fn apply_fades(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fade_query: Query<(Entity, &mut AudioFade, &mut AudioGain)>,
+) {
+    for (entity, mut fade, mut gain) in fade_query.iter_mut() {
+        gain.0 = fade.tick(time.delta());
+
+        if fade.is_finished() {
+            if fade.despawn {
+                commands.entity(entity).despawn();
+            } else {
+                commands.entity(entity).remove::<AudioFade>();
+            }
+        }
+    }
+}
+fn apply_volumes(
+    volumes: Res<AudioVolumes>,
+    mut sink_query: Query<(&AudioChannel, &AudioGain, &mut AudioSink)>,
+) {
+    for (channel, gain, mut sink) in sink_query.iter_mut() {
+        sink.set_volume(volumes.volume(*channel, gain.0));
+    }
+}
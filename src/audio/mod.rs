@@ -0,0 +1,9 @@
+pub mod controller;
+
+pub use controller::AudioChangeMessage;
+pub use controller::AudioChannel;
+pub use controller::AudioFade;
+pub use controller::AudioController;
+pub use controller::AudioOperation;
+pub use controller::AudioVolumes;
+pub use controller::CurrentMusic;
\ No newline at end of file
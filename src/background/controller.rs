@@ -1,12 +1,16 @@
 use std::collections::HashMap;
 
-use bevy::asset::{LoadState, LoadedFolder};
+use bevy::asset::io::AssetReaderError;
+use bevy::asset::{AssetLoadError, LoadState, LoadedFolder};
 use bevy::prelude::*;
+use bevy::sprite_render::Material2dPlugin;
 use bevy::{app::{App, Plugin}, asset::{AssetServer, Handle}};
 use anyhow::Context;
 
-use crate::compiler::controller::{Controller, ControllerReadyMessage, TriggerControllersMessage};
-use crate::{CharacterConfig, Object};
+use crate::background::transition::{run_transitions, start_transition, BackgroundTransition, TransitionEffect, TransitionMaterial, TransitionOverlay};
+use crate::compiler::controller::{Controller, ControllerReadyMessage, ScriptSystems, TriggerControllersMessage};
+use crate::display::DESIGN_RESOLUTION;
+use crate::{CharacterConfig, Object, VisualNovelState};
 
 /* States */
 #[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
@@ -20,17 +24,24 @@ enum BackgroundControllerState {
 /* Components */
 #[derive(Component)]
 pub struct Background {
-    pub background_sprites: HashMap::<String, Handle<Image>>
+    pub background_sprites: HashMap::<String, Handle<Image>>,
+    // Grayscale images from `transitions/` for mask dissolves
+    pub mask_sprites: HashMap::<String, Handle<Image>>,
+    pub current: Option<String>,
 }
 
 /* Resources */
 #[derive(Resource)]
 struct HandleToBackgroundsFolder(Handle<LoadedFolder>);
+#[derive(Resource)]
+struct HandleToMasksFolder(Handle<LoadedFolder>);
 
 /* Messages */
 #[derive(Message)]
 pub struct BackgroundChangeMessage {
-    pub background_id: String
+    pub background_id: String,
+    // Switches at once without one
+    pub transition: Option<BackgroundTransition>,
 }
 
 pub struct BackgroundController;
@@ -41,7 +52,8 @@ impl Plugin for BackgroundController {
             .add_systems(OnEnter(BackgroundControllerState::Loading), import_backgrounds)
             .add_systems(Update, setup.run_if(in_state(BackgroundControllerState::Loading)))
             .add_systems(Update, wait_trigger.run_if(in_state(BackgroundControllerState::Idle)))
-            .add_systems(Update, update_background.run_if(in_state(BackgroundControllerState::Running)));
+            .add_plugins(Material2dPlugin::<TransitionMaterial>::default())
+            .add_systems(Update, (update_background, run_transitions).chain().after(ScriptSystems).run_if(in_state(BackgroundControllerState::Running)));
     }
 }
 fn setup(
@@ -49,50 +61,78 @@ fn setup(
     asset_server: Res<AssetServer>,
     loaded_folders: Res<Assets<LoadedFolder>>,
     folder_handle: Res<HandleToBackgroundsFolder>,
+    masks_folder_handle: Res<HandleToMasksFolder>,
     mut controller_state: ResMut<NextState<BackgroundControllerState>>,
     mut msg_writer: MessageWriter<ControllerReadyMessage>,
 ) -> Result<(), BevyError> {
-    let mut background_sprites: HashMap<String, Handle<Image>>= HashMap::new();
+    let (Some(state), Some(masks_state)) = (
+        asset_server.get_load_state(folder_handle.0.id()),
+        asset_server.get_load_state(masks_folder_handle.0.id()),
+    ) else {
+        return Ok(());
+    };
+    if let LoadState::Failed(e) = &state {
+        return Err(anyhow::anyhow!("Error loading background assets: {}", e).into());
+    }
+    // Games without masks needn't have the folder at all
+    let no_masks = folder_missing(&masks_state);
+    if let LoadState::Failed(e) = &masks_state && !no_masks {
+        return Err(anyhow::anyhow!("Error loading transition masks: {}", e).into());
+    }
+    if !state.is_loaded() || !(masks_state.is_loaded() || no_masks) {
+        return Ok(());
+    }
 
-    if let Some(state) = asset_server.get_load_state(folder_handle.0.id()) {
-        match state {
-            LoadState::Loaded => {
-                if let Some(loaded_folder) = loaded_folders.get(folder_handle.0.id()) {
-                    for handle in &loaded_folder.handles {
-                        let path = handle.path()
-                            .context("Error retrieving background path")?;
-                        let filename = path.path().file_stem()
-                            .context("Background file has no name")?
-                            .to_string_lossy()
-                            .to_string();
-                        background_sprites.insert(filename, handle.clone().typed());
-                    }
-                }
+    let background_sprites = folder_images(&loaded_folders, &folder_handle.0)
+        .context("Error retrieving backgrounds")?;
+    let mask_sprites = folder_images(&loaded_folders, &masks_folder_handle.0)
+        .context("Error retrieving transition masks")?;
 
-                /* Background Setup */
-                commands.spawn((
-                    Object {
-                        id: String::from("_primary")
-                    },
-                    Background {
-                        background_sprites,
-                    },
-                    Sprite::default()
-                ));
-                controller_state.set(BackgroundControllerState::Idle);
-                msg_writer.write(ControllerReadyMessage(Controller::Background));
-            },
-            LoadState::Failed(e) => {
-                return Err(anyhow::anyhow!("Error loading background assets: {}", e.to_string()).into());
-            }
-            _ => {}
+    /* Background Setup */
+    commands.spawn((
+        Object {
+            id: String::from("_primary")
+        },
+        Background {
+            background_sprites,
+            mask_sprites,
+            current: None,
+        },
+        // Art of any size fills the screen
+        Sprite {
+            custom_size: Some(DESIGN_RESOLUTION),
+            ..default()
         }
-    }
+    ));
+    controller_state.set(BackgroundControllerState::Idle);
+    msg_writer.write(ControllerReadyMessage(Controller::Background));
     Ok(())
 }
+// Whether a folder failed to load only because it isn't there
+pub fn folder_missing(state: &LoadState) -> bool {
+    matches!(state, LoadState::Failed(error) if matches!(**error, AssetLoadError::AssetReaderError(AssetReaderError::NotFound(_))))
+}
+// Every image in a loaded folder, by file name, or none if it never loaded
+fn folder_images(loaded_folders: &Assets<LoadedFolder>, folder_handle: &Handle<LoadedFolder>) -> anyhow::Result<HashMap<String, Handle<Image>>> {
+    let mut images = HashMap::new();
+    if let Some(loaded_folder) = loaded_folders.get(folder_handle.id()) {
+        for handle in &loaded_folder.handles {
+            let path = handle.path()
+                .context("Error retrieving image path")?;
+            let filename = path.path().file_stem()
+                .context("Image file has no name")?
+                .to_string_lossy()
+                .to_string();
+            images.insert(filename, handle.clone().typed());
+        }
+    }
+    Ok(images)
+}
 pub fn import_backgrounds(mut commands: Commands, asset_server: Res<AssetServer>){
     let loaded_folder = asset_server.load_folder("backgrounds");
     commands.insert_resource(HandleToBackgroundsFolder(loaded_folder));
+    let masks_folder = asset_server.load_folder("transitions");
+    commands.insert_resource(HandleToMasksFolder(masks_folder));
 }
 fn wait_trigger(
     mut msg_reader: MessageReader<TriggerControllersMessage>,
@@ -103,18 +143,41 @@ fn wait_trigger(
     }
 }
 pub fn update_background(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<TransitionMaterial>>,
     mut background_query: Query<(
-        &Background,
+        &mut Background,
         &mut Sprite
     ), (With<Background>, Without<CharacterConfig>)>,
+    overlay_query: Query<Entity, With<TransitionOverlay>>,
 
     mut background_change_message: MessageReader<BackgroundChangeMessage>,
+    game_state: Res<VisualNovelState>,
 ) -> Result<(), BevyError> {
     for msg in background_change_message.read() {
-        for (background_obj, mut current_sprite) in background_query.iter_mut() {
+        for (mut background_obj, mut current_sprite) in background_query.iter_mut() {
             let background_handle = background_obj.background_sprites.get(&msg.background_id)
-                .with_context(|| format!("Background '{}' does not exist", msg.background_id))?;
+                .with_context(|| game_state.diagnostic(format!("Background '{}' does not exist", msg.background_id)))?;
+
+            // A transition still playing is cut short
+            for entity in overlay_query.iter() {
+                commands.entity(entity).despawn();
+            }
+
+            // The new background goes in underneath, for the transition to reveal
+            if let Some(transition) = &msg.transition && transition.duration > 0. {
+                let mask = match &transition.effect {
+                    TransitionEffect::Dissolve(Some(mask_id)) => Some(background_obj.mask_sprites.get(mask_id)
+                        .with_context(|| game_state.diagnostic(format!("Transition mask '{}' does not exist", mask_id)))?
+                        .clone()),
+                    _ => None,
+                };
+                let material = TransitionMaterial::new(&transition.effect, current_sprite.image.clone(), background_handle.clone(), mask);
+                start_transition(&mut commands, &mut meshes, &mut materials, DESIGN_RESOLUTION, material, transition.duration);
+            }
             current_sprite.image = background_handle.clone();
+            background_obj.current = Some(msg.background_id.clone());
             println!("[ Set background to '{}']", msg.background_id);
         }
     }
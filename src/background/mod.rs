@@ -1,4 +1,10 @@
 pub mod controller;
+pub mod transition;
 
+pub use controller::Background;
 pub use controller::BackgroundController;
 pub use controller::BackgroundChangeMessage;
+
+pub use transition::BackgroundTransition;
+pub use transition::TransitionEffect;
+pub use transition::WipeDirection;
@@ -0,0 +1,137 @@
+use crate::tween::{Progress, Tween, TweenExt, TweenTarget};
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderType};
+use bevy::shader::ShaderRef;
+use bevy::sprite_render::{AlphaMode2d, Material2d};
+use serde::{Deserialize, Serialize};
+
+// Between the background and the characters, who stand from z = 1
+const OVERLAY_Z: f32 = 0.5;
+// Fraction of the screen a wipe or mask edge blurs over
+const EDGE_SOFTNESS: f32 = 0.1;
+
+/* Custom types */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+impl WipeDirection {
+    // Which way the edge travels, in texture coordinates where y points down
+    fn vector(&self) -> Vec2 {
+        match self {
+            WipeDirection::Left => Vec2::NEG_X,
+            WipeDirection::Right => Vec2::X,
+            WipeDirection::Up => Vec2::NEG_Y,
+            WipeDirection::Down => Vec2::Y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransitionEffect {
+    // Crossfades, or with a mask, changes the darkest parts of the mask first
+    Dissolve(Option<String>),
+    Wipe(WipeDirection),
+    FadeThroughBlack,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackgroundTransition {
+    pub effect: TransitionEffect,
+    pub duration: f32,
+}
+
+// Must match `TransitionSettings` in `background_transition.wgsl`
+#[derive(Debug, Clone, Copy, Default, ShaderType)]
+pub struct TransitionSettings {
+    pub progress: f32,
+    pub effect: u32,
+    pub softness: f32,
+    pub direction: Vec2,
+}
+
+// Draws the old background turning into the new one
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct TransitionMaterial {
+    #[uniform(0)]
+    pub settings: TransitionSettings,
+    #[texture(1)]
+    #[sampler(2)]
+    pub from: Handle<Image>,
+    #[texture(3)]
+    #[sampler(4)]
+    pub to: Handle<Image>,
+    #[texture(5)]
+    #[sampler(6)]
+    pub mask: Option<Handle<Image>>,
+}
+impl Material2d for TransitionMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/background_transition.wgsl".into()
+    }
+
+    // Sorted with the sprites, so it covers the background but not the characters
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+}
+impl TransitionMaterial {
+    pub fn new(effect: &TransitionEffect, from: Handle<Image>, to: Handle<Image>, mask: Option<Handle<Image>>) -> Self {
+        let (effect, direction) = match effect {
+            TransitionEffect::Dissolve(None) => (0, Vec2::ZERO),
+            TransitionEffect::Dissolve(Some(_)) => (1, Vec2::ZERO),
+            TransitionEffect::Wipe(direction) => (2, direction.vector()),
+            TransitionEffect::FadeThroughBlack => (3, Vec2::ZERO),
+        };
+        Self {
+            settings: TransitionSettings {
+                progress: 0.,
+                effect,
+                softness: EDGE_SOFTNESS,
+                direction,
+            },
+            from,
+            to,
+            mask,
+        }
+    }
+}
+
+/* Components */
+// A transition playing over the background, which already shows the new one underneath
+#[derive(Component)]
+pub struct TransitionOverlay;
+
+pub fn start_transition(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<TransitionMaterial>,
+    size: Vec2,
+    material: TransitionMaterial,
+    duration: f32,
+) {
+    commands.spawn((
+        TransitionOverlay,
+        Progress::default(),
+        Mesh2d(meshes.add(Rectangle::from_size(size))),
+        MeshMaterial2d(materials.add(material)),
+        Transform::from_xyz(0., 0., OVERLAY_Z),
+    ))
+    .tween(Tween::new(TweenTarget::Progress(1.), duration).blocking().despawn_when_done());
+}
+
+// Hands each transition's progress to its shader
+pub fn run_transitions(
+    mut materials: ResMut<Assets<TransitionMaterial>>,
+    overlay_query: Query<(&Progress, &MeshMaterial2d<TransitionMaterial>), Changed<Progress>>,
+) {
+    for (progress, material) in overlay_query.iter() {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.settings.progress = progress.0;
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use sabi::compiler::ast::{Act, Acts, Expr, StageCommand, Statement, StatementKind};
+use sabi::compiler::controller::compile_acts;
+use sabi::compiler::diagnostics::Span;
+use sabi::compiler::validation::{validate_acts, AssetCatalog};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context, Result};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Parser)]
+#[command(name = "sabi-cli", about = "Compile and check Sabi scripts without starting the game")]
+struct Cli {
+    /// Folder holding the game's assets
+    #[arg(long, global = true, default_value = "assets")]
+    assets: PathBuf,
+    /// Folder holding the acts [default: <ASSETS>/acts]
+    #[arg(long, global = true)]
+    acts: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Parse every act and check its scene, act and asset references
+    Check,
+    /// Print the compiled acts as JSON
+    Dump {
+        /// Only dump this act
+        act: Option<String>,
+        /// One entry per statement instead of the nested tree
+        #[arg(long)]
+        flat: bool,
+    },
+    /// Print the scene flow as a Graphviz graph
+    Graph,
+    /// Count dialogue lines and words per character and scene
+    Stats,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let acts_path = cli.acts.clone()
+        .unwrap_or_else(|| cli.assets.join("acts"));
+    let acts = compile_acts(&acts_path)
+        .with_context(|| format!("Failed to compile the acts in {:?}", acts_path))?;
+
+    match cli.command {
+        Command::Check => check(&acts, &cli.assets),
+        Command::Dump { act, flat } => dump(&acts, act, flat),
+        Command::Graph => {
+            graph(&acts);
+            Ok(())
+        },
+        Command::Stats => {
+            stats(&acts);
+            Ok(())
+        },
+    }
+}
+
+// Acts and scenes by id, so output doesn't change between runs
+fn sorted_acts(acts: &Acts) -> BTreeMap<&String, &Act> {
+    acts.iter()
+        .map(|(act_id, act)| (act_id, act.as_ref()))
+        .collect()
+}
+fn sorted_scenes(act: &Act) -> BTreeMap<&String, &Vec<Statement>> {
+    act.scenes.iter()
+        .map(|(scene_id, scene)| (scene_id, &scene.statements))
+        .collect()
+}
+
+// Visits every statement, nested blocks included, in script order
+fn walk<'a>(statements: &'a [Statement], depth: usize, visit: &mut impl FnMut(&'a Statement, usize)) {
+    for statement in statements {
+        visit(statement, depth);
+        let mut branch = 0;
+        while let Some(block) = statement.block(branch) {
+            walk(block, depth + 1, visit);
+            branch += 1;
+        }
+    }
+}
+
+fn check(acts: &Acts, assets_path: &PathBuf) -> Result<()> {
+    let catalog = AssetCatalog::from_dir(assets_path)
+        .with_context(|| format!("Failed to read the assets in {:?}", assets_path))?;
+    let errors = validate_acts(acts, &catalog);
+    for error in &errors {
+        eprintln!("{}\n", error.span.render(&error.message));
+    }
+    ensure!(errors.is_empty(), "Found {} problem(s) in the scripts", errors.len());
+
+    println!("Checked {} act(s), no problems found", acts.len());
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct FlatStatement<'a> {
+    act: &'a str,
+    scene: &'a str,
+    depth: usize,
+    span: &'a Span,
+    statement: serde_json::Value,
+}
+
+// A statement without its nested blocks, which follow it in the flat dump
+fn shallow(kind: &StatementKind) -> Result<serde_json::Value> {
+    Ok(match kind {
+        StatementKind::If { cond, .. } => json!({ "If": { "cond": cond } }),
+        StatementKind::Choice { prompt, options } => {
+            let labels: Vec<&Expr> = options.iter().map(|option| &option.label).collect();
+            json!({ "Choice": { "prompt": prompt, "options": labels } })
+        },
+        other => serde_json::to_value(other)?,
+    })
+}
+
+fn dump(acts: &Acts, act_filter: Option<String>, flat: bool) -> Result<()> {
+    let mut selected = sorted_acts(acts);
+    if let Some(act_id) = &act_filter {
+        ensure!(selected.contains_key(act_id), "Act '{}' does not exist", act_id);
+        selected.retain(|id, _| *id == act_id);
+    }
+
+    let output = if flat {
+        let mut statements = Vec::new();
+        for (act_id, act) in &selected {
+            for (scene_id, scene) in sorted_scenes(act) {
+                let mut result = Ok(());
+                walk(scene, 0, &mut |statement, depth| {
+                    match shallow(&statement.kind) {
+                        Ok(value) => statements.push(FlatStatement {
+                            act: act_id,
+                            scene: scene_id,
+                            depth,
+                            span: &statement.span,
+                            statement: value,
+                        }),
+                        Err(error) => result = Err(error),
+                    }
+                });
+                result?;
+            }
+        }
+        serde_json::to_string_pretty(&statements)?
+    } else {
+        let tree: BTreeMap<&String, BTreeMap<&String, &Vec<Statement>>> = selected.iter()
+            .map(|(act_id, act)| (*act_id, sorted_scenes(act)))
+            .collect();
+        serde_json::to_string_pretty(&tree)?
+    };
+
+    println!("{}", output);
+    Ok(())
+}
+
+fn scene_target(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::String(target) => Some(target),
+        _ => None,
+    }
+}
+
+// Collects the scenes a block can move to, labelled with the choice that leads there
+fn edges<'a>(statements: &'a [Statement], label: Option<&'a Expr>, act_id: &'a str, acts: &'a Acts, found: &mut Vec<(String, Option<&'a Expr>)>) {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Stage(StageCommand::SceneChange { scene_expr }) => {
+                if let Some(scene_id) = scene_target(scene_expr) {
+                    found.push((format!("{}/{}", act_id, scene_id), label));
+                }
+            },
+            StatementKind::Stage(StageCommand::ActChange { act_expr }) => {
+                if let Some(act) = scene_target(act_expr).and_then(|target| acts.get_key_value(target)) {
+                    found.push((format!("{}/{}", act.0, act.1.entrypoint), label));
+                }
+            },
+            StatementKind::If { then_block, else_block, .. } => {
+                edges(then_block, label, act_id, acts, found);
+                edges(else_block, label, act_id, acts, found);
+            },
+            StatementKind::Choice { options, .. } => {
+                for option in options {
+                    edges(&option.block, Some(&option.label), act_id, acts, found);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn graph(acts: &Acts) {
+    println!("digraph sabi {{");
+    for (act_id, act) in sorted_acts(acts) {
+        println!("    subgraph \"cluster_{}\" {{", act_id);
+        println!("        label = \"Act {}\";", act_id);
+        for scene_id in sorted_scenes(act).keys() {
+            let shape = if **scene_id == act.entrypoint { "doublecircle" } else { "circle" };
+            println!("        \"{}/{}\" [label = \"{}\", shape = {}];", act_id, scene_id, scene_id, shape);
+        }
+        println!("    }}");
+    }
+    for (act_id, act) in sorted_acts(acts) {
+        for (scene_id, statements) in sorted_scenes(act) {
+            let mut found = Vec::new();
+            edges(statements, None, act_id, acts, &mut found);
+            for (target, label) in found {
+                match label {
+                    Some(Expr::String(label)) => println!("    \"{}/{}\" -> \"{}\" [label = {:?}];", act_id, scene_id, target, label),
+                    _ => println!("    \"{}/{}\" -> \"{}\";", act_id, scene_id, target),
+                }
+            }
+        }
+    }
+    println!("}}");
+}
+
+// Words in the literal parts of a line; variables aren't known until the game runs
+fn literal_words(expr: &Expr) -> usize {
+    match expr {
+        Expr::String(text) => text.split_whitespace().count(),
+        Expr::Add { lhs, rhs } => literal_words(lhs) + literal_words(rhs),
+        Expr::Interpolation { parts } => parts.iter().map(literal_words).sum(),
+        _ => 0,
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    lines: usize,
+    words: usize,
+}
+fn print_counts(title: &str, counts: &BTreeMap<String, Counts>) {
+    let width = counts.keys().map(|name| name.len()).max().unwrap_or(0);
+    println!("{}", title);
+    for (name, count) in counts {
+        println!("  {:<width$}  {:>6} lines  {:>7} words", name, count.lines, count.words);
+    }
+}
+
+fn stats(acts: &Acts) {
+    let mut characters: BTreeMap<String, Counts> = BTreeMap::new();
+    let mut scenes: BTreeMap<String, Counts> = BTreeMap::new();
+    let mut total = Counts::default();
+
+    for (act_id, act) in sorted_acts(acts) {
+        for (scene_id, statements) in sorted_scenes(act) {
+            let scene_counts = scenes.entry(format!("{}/{}", act_id, scene_id)).or_default();
+            walk(statements, 0, &mut |statement, _| {
+                if let StatementKind::Dialogue(dialogue) = &statement.kind {
+                    let words = literal_words(&dialogue.dialogue);
+                    for counts in [characters.entry(dialogue.character.clone()).or_default(), &mut *scene_counts] {
+                        counts.lines += 1;
+                        counts.words += words;
+                    }
+                    total.lines += 1;
+                    total.words += words;
+                }
+            });
+        }
+    }
+
+    print_counts("Characters", &characters);
+    println!();
+    print_counts("Scenes", &scenes);
+    println!();
+    println!("Total: {} lines, {} words", total.lines, total.words);
+}
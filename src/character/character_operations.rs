@@ -1,8 +1,7 @@
-use std::ops::Index;
-
+use anyhow::Context;
 use bevy::prelude::*;
 
-use crate::{character::{controller::{FadingCharacters, SpriteKey}, CharacterConfig, CharactersResource}, Object, VisualNovelState};
+use crate::{character::{controller::SpriteKey, stage::{stage_transform, StagePlacement}, CharacterConfig, CharactersResource}, tween::{Tween, TweenExt, TweenTarget}, Object};
 
 pub fn change_character_emotion(
     sprite: &mut Sprite,
@@ -17,55 +16,14 @@ pub fn change_character_emotion(
    };
    let image = sprites.0.get(&sprite_key).with_context(|| format!("Sprite not found for {:?}", sprite_key))?;
    sprite.image = image.clone();
-}
-pub fn apply_alpha(
-    mut commands: Commands,
-    mut query: Query<&mut Sprite>,
-    mut fading_characters: ResMut<FadingCharacters>,
-    mut game_state: ResMut<VisualNovelState>,
-) {
-    if fading_characters.0.is_empty() {
-        return;
-    }
-
-    let mut finished_anim: Vec<Entity> = Vec::new();
-    for fading_char in &fading_characters.0 {
-        let mut s = match query.get_mut(fading_char.0) {
-            Ok(e) => e,
-            Err(_) => continue
-        };
-        let mut color = s.color;
-        color.set_alpha(s.color.alpha() + fading_char.1);
-        s.color = color;
-        if color.alpha() >= 1. || color.alpha() <= 0. {
-            finished_anim.push(fading_char.0);
-        }
-    }
-    let mut to_remove: Vec<usize> = Vec::new();
-    let _ = fading_characters.0.iter().enumerate().for_each(|f| {
-        if finished_anim.contains(&f.1.0) {
-            to_remove.push(f.0);
-        }
-    });
-    to_remove.reverse();
-    for index in to_remove {
-        let item = fading_characters.0.index(index);
-        let to_despawn = item.2;
-        if to_despawn {
-            commands.entity(item.0).despawn();
-        }
-        fading_characters.0.remove(index);
-    }
-    if fading_characters.0.is_empty() {
-        game_state.blocking = false;
-    }
+   Ok(())
 }
 pub fn spawn_character(
     commands: &mut Commands,
     character_config: CharacterConfig,
+    placement: StagePlacement,
     sprites: &Res<CharactersResource>,
-    fading: &bool,
-    fading_characters: &mut ResMut<FadingCharacters>,
+    fade: f32,
 ) {
     let sprite_key = SpriteKey {
         character: character_config.name.clone(),
@@ -85,25 +43,17 @@ pub fn spawn_character(
         },
         Sprite {
             image,
-            color: Color::default().with_alpha(if *fading {
+            color: Color::default().with_alpha(if fade > 0. {
                 0.
             } else { 1. }),
             ..default()
         },
-        Transform::default()
-            .with_translation(Vec3 {
-                x: 0.,
-                y: -40.,
-                z: 1.,
-            })
-            .with_scale(Vec3 {
-                x: 0.75,
-                y: 0.75,
-                z: 1.,
-            }),
+        // Moved into place once the stage is laid out
+        stage_transform(0., placement.order),
+        placement,
         character_config
     )).id();
-    if *fading {
-        fading_characters.0.push((entity, 0.01, false));
+    if fade > 0. {
+        commands.entity(entity).tween(Tween::new(TweenTarget::Alpha(1.), fade).blocking());
     }
 }
\ No newline at end of file
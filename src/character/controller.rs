@@ -2,9 +2,13 @@ use std::collections::HashMap;
 
 use anyhow::{Result, Context};
 use bevy::{asset::{LoadState, LoadedFolder}, prelude::*};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{character::character_operations::{apply_alpha, change_character_emotion, spawn_character}, compiler::controller::{Controller, ControllerReadyMessage, TriggerControllersMessage}, ChatScrollStopwatch, GUIScrollText, VisualNovelState};
+use crate::character::speaking::{animate_speakers, load_speaking_style, SpeakingStyle};
+use crate::character::stage::{layout_stage, StagePlacement, StageSlot};
+use crate::tween::{Tween, TweenExt, TweenTarget};
+use crate::chat::theme::deserialize_color;
+use crate::{character::character_operations::{change_character_emotion, spawn_character}, compiler::controller::{Controller, ControllerReadyMessage, ScriptSystems, TriggerControllersMessage}, ChatScrollStopwatch, GUIScrollText, VisualNovelState};
 
 /* States */
 #[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
@@ -23,6 +27,33 @@ pub struct CharacterConfig {
     pub description: String,
     pub emotions: Vec<String>,
     pub outfits: Vec<String>,
+
+    // Chat styling while this character speaks; anything unset comes from the chat theme
+    pub textbox: Option<String>,
+    pub namebox: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub name_color: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub text_color: Option<Color>,
+    pub font: Option<String>,
+    // Seconds between typed characters in this character's lines
+    pub character_delay: Option<f32>,
+}
+
+impl CharacterConfig {
+    // The emotion to show in `outfit`: the current one if it has a sprite there,
+    //  otherwise the first listed emotion that does
+    pub fn emotion_in_outfit(&self, outfit: &str, sprites: &CharacterSprites) -> Option<String> {
+        let has_sprite = |emotion: &String| sprites.contains_key(&SpriteKey {
+            character: self.name.clone(),
+            outfit: outfit.to_owned(),
+            emotion: emotion.clone(),
+        });
+        std::iter::once(&self.emotion)
+            .chain(&self.emotions)
+            .find(|emotion| has_sprite(emotion))
+            .cloned()
+    }
 }
 
 /* Resources */
@@ -31,9 +62,7 @@ struct HandleToCharactersFolder(Handle<LoadedFolder>);
 #[derive(Resource)]
 pub struct CharactersResource(pub CharacterSprites);
 #[derive(Resource)]
-struct Configs(CharactersConfig);
-#[derive(Resource, Default)]
-pub struct FadingCharacters(pub Vec<(Entity, f32, bool)>); // entity, alpha_step, to_despawn
+pub struct Configs(pub CharactersConfig);
 
 /* Custom types */
 #[derive(Hash, Eq, PartialEq, Debug)]
@@ -42,14 +71,16 @@ pub struct SpriteKey {
     pub outfit: String,
     pub emotion: String,
 }
-type CharacterSprites = HashMap<SpriteKey, Handle<Image>>;
-type CharactersConfig = HashMap<String, CharacterConfig>;
+pub type CharacterSprites = HashMap<SpriteKey, Handle<Image>>;
+pub type CharactersConfig = HashMap<String, CharacterConfig>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum CharacterOperation {
-    Spawn(Option<String>, bool), // emotion, fading
+    Spawn(Option<String>, Option<StageSlot>, f32), // emotion, position, fade seconds
     EmotionChange(String),
-    Despawn(bool), // fading
+    Despawn(f32), // fade seconds
+    Move(StageSlot, f32), // position, seconds
+    OutfitChange(String, f32), // outfit, crossfade seconds
 }
 
 /* Messages */
@@ -59,26 +90,17 @@ pub struct CharacterChangeMessage {
     pub operation: CharacterOperation,
 }
 
-impl CharacterChangeMessage {
-    pub fn is_blocking(&self) -> bool {
-        if let CharacterOperation::Spawn(_, true) = self.operation {
-            true
-        } else if let CharacterOperation::Despawn(true) = self.operation {
-            true
-        } else { false }
-    }
-}
-
 pub struct CharacterController;
 impl Plugin for CharacterController {
     fn build(&self, app: &mut App) {
-        app.insert_resource(FadingCharacters::default())
+        app.init_resource::<SpeakingStyle>()
             .add_message::<CharacterChangeMessage>()
             .init_state::<CharacterControllerState>()
+            .add_systems(Startup, load_speaking_style)
             .add_systems(OnEnter(CharacterControllerState::Loading), import_characters)
             .add_systems(Update, setup.run_if(in_state(CharacterControllerState::Loading)))
             .add_systems(Update, wait_trigger.run_if(in_state(CharacterControllerState::Idle)))
-            .add_systems(Update, (update_characters, apply_alpha).run_if(in_state(CharacterControllerState::Running)));
+            .add_systems(Update, ((update_characters, layout_stage).chain().after(ScriptSystems), animate_speakers.after(update_characters)).run_if(in_state(CharacterControllerState::Running)));
     }
 }
 fn define_characters_map(
@@ -177,33 +199,34 @@ fn wait_trigger(
 }
 fn update_characters(
     mut commands: Commands,
-    mut character_query: Query<(Entity, &mut CharacterConfig, &mut Sprite)>,
+    mut character_query: Query<(Entity, &mut CharacterConfig, &mut Sprite, &mut StagePlacement, &Transform)>,
+    // Counts arrivals, so whoever came on stage last is drawn in front
+    mut arrivals: Local<u32>,
     sprites: Res<CharactersResource>,
     mut configs: ResMut<Configs>,
-    mut fading_characters: ResMut<FadingCharacters>,
     mut character_change_message: MessageReader<CharacterChangeMessage>,
-    mut game_state: ResMut<VisualNovelState>,
+    game_state: Res<VisualNovelState>,
 
     _text_object_query: Query<(&mut Text, &mut GUIScrollText)>,
     _scroll_stopwatch: ResMut<ChatScrollStopwatch>,
 ) -> Result<(), BevyError> {
     for msg in character_change_message.read() {
-        let character_config = configs.0.get_mut(&msg.character).context(format!("Character config not found for {}", &msg.character))?;
+        let character_config = configs.0.get_mut(&msg.character)
+            .with_context(|| game_state.diagnostic(format!("Character config not found for {}", &msg.character)))?;
         match &msg.operation {
-            CharacterOperation::Spawn(emotion, fading) => {
+            CharacterOperation::Spawn(emotion, slot, fade) => {
                 let emotion = if let Some(e) = emotion { e } else { &character_config.emotion };
                 character_config.emotion = emotion.clone();
                 if let Some(_) = character_query.iter_mut().find(|entity| entity.1.name == character_config.name) {
                     warn!("Another instance of the character is already in the World!");
                 }
-                spawn_character(&mut commands, character_config.clone(), &sprites, fading, &mut fading_characters);
-                if *fading {
-                    game_state.blocking = true;
-                }
+                *arrivals += 1;
+                let placement = StagePlacement { slot: *slot, order: *arrivals };
+                spawn_character(&mut commands, character_config.clone(), placement, &sprites, *fade);
             },
             CharacterOperation::EmotionChange(emotion) => {
                 if !character_config.emotions.contains(&emotion) {
-                    return Err(anyhow::anyhow!("Character does not have {} emotion!", emotion).into());
+                    return Err(anyhow::anyhow!(game_state.diagnostic(format!("Character does not have {} emotion!", emotion))).into());
                 }
                 let mut entity = match character_query.iter_mut().find(|entity| entity.1.name == character_config.name) {
                     Some(e) => e,
@@ -213,20 +236,64 @@ fn update_characters(
                         return Ok(());
                     }
                 };
-                change_character_emotion(&mut entity.2, &sprites, emotion, character_config);
+                change_character_emotion(&mut entity.2, &sprites, emotion, character_config)
+                    .map_err(|error| anyhow::anyhow!(game_state.diagnostic(error)))?;
+                entity.1.emotion = emotion.clone();
             },
-            CharacterOperation::Despawn(fading) => {
-                if *fading {
+            CharacterOperation::Despawn(fade) => {
+                if *fade > 0. {
                     for entity in character_query.iter().filter(|c| c.1.name == character_config.name) {
-                        fading_characters.0.push((entity.0, -0.01, true));
+                        commands.entity(entity.0).tween(Tween::new(TweenTarget::Alpha(0.), *fade).blocking().despawn_when_done());
                     }
-                    game_state.blocking = true;
                 } else {
                     for entity in character_query.iter().filter(|c| c.1.name == character_config.name) {
                         commands.entity(entity.0).despawn();
                     }
                 }
-            }
+            },
+            CharacterOperation::OutfitChange(outfit, crossfade) => {
+                if !character_config.outfits.contains(outfit) {
+                    return Err(anyhow::anyhow!(game_state.diagnostic(format!("Character does not have {} outfit!", outfit))).into());
+                }
+                let emotion = character_config.emotion_in_outfit(outfit, &sprites.0)
+                    .with_context(|| game_state.diagnostic(format!("Character {} has no sprites for outfit {}", character_config.name, outfit)))?;
+                if emotion != character_config.emotion {
+                    warn!("Outfit {} has no {} sprite for {}, showing {} instead", outfit, character_config.emotion, character_config.name, emotion);
+                }
+                character_config.outfit = outfit.clone();
+                character_config.emotion = emotion.clone();
+
+                // Off stage, the outfit is simply worn next time they appear
+                let Some((entity, mut config, mut sprite, _, _)) = character_query.iter_mut().find(|entity| entity.1.name == character_config.name) else {
+                    continue;
+                };
+                if *crossfade > 0. {
+                    // The old outfit fades away just in front of the new one
+                    let old_outfit = commands.spawn((
+                        Sprite {
+                            image: sprite.image.clone(),
+                            color: sprite.color,
+                            ..default()
+                        },
+                        Transform::from_xyz(0., 0., 0.001),
+                    ))
+                    .tween(Tween::new(TweenTarget::Alpha(0.), *crossfade).blocking().despawn_when_done())
+                    .id();
+                    commands.entity(entity).add_child(old_outfit);
+                }
+                change_character_emotion(&mut sprite, &sprites, &emotion, character_config)
+                    .map_err(|error| anyhow::anyhow!(game_state.diagnostic(error)))?;
+                config.outfit = outfit.clone();
+                config.emotion = emotion;
+            },
+            CharacterOperation::Move(slot, duration) => {
+                let Some((entity, _, _, mut placement, transform)) = character_query.iter_mut().find(|entity| entity.1.name == character_config.name) else {
+                    warn!("Character {} not found in the World!", character_config.name);
+                    continue;
+                };
+                placement.slot = Some(*slot);
+                commands.entity(entity).tween(Tween::new(TweenTarget::Translation(transform.translation.with_x(slot.x())), *duration));
+            },
         }
     }
 
@@ -1,9 +1,18 @@
 mod character_operations;
 pub mod controller;
+pub mod speaking;
+pub mod stage;
 
 pub use controller::CharacterChangeMessage;
 pub use controller::CharacterConfig;
 pub use controller::CharacterController;
+pub use controller::Configs;
 pub use controller::CharacterOperation;
+pub use controller::CharactersConfig;
 pub use controller::CharactersResource;
 
+pub use speaking::SpeakingStyle;
+
+pub use stage::StagePlacement;
+pub use stage::StageSlot;
+
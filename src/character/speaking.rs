@@ -0,0 +1,116 @@
+use crate::character::controller::SpriteKey;
+use crate::character::{CharacterConfig, CharactersResource};
+use crate::chat::{CharacterSayMessage, MarkupText};
+use crate::settings::read_settings;
+use crate::tween::{Tween, TweenExt, TweenTarget, Tweens};
+use crate::Object;
+
+use std::path::PathBuf;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+use serde::{Deserialize, Serialize};
+
+// Sprites shown while talking are named after the emotion, like `happyspeaking.png`
+pub const SPEAKING_SUFFIX: &str = "speaking";
+// Seconds listeners take to dim, or to brighten once they speak
+const DIM_DURATION: f32 = 0.15;
+
+/* Resources */
+// How characters on stage react to who's talking; `settings/stage.json` overrides any of it
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeakingStyle {
+    // Seconds between mouth movements while a line types out; 0 keeps the mouth open
+    pub mouth_flap: f32,
+    // How bright everyone else on stage is while a character speaks; 1 leaves them be
+    pub listener_brightness: f32,
+}
+impl Default for SpeakingStyle {
+    fn default() -> Self {
+        Self {
+            mouth_flap: 0.12,
+            listener_brightness: 0.6,
+        }
+    }
+}
+impl SpeakingStyle {
+    // Whether the mouth is open this far into a line, starting open
+    pub fn mouth_open(&self, elapsed: f32) -> bool {
+        self.mouth_flap <= 0. || ((elapsed / self.mouth_flap) as u32).is_multiple_of(2)
+    }
+}
+
+pub fn speaking_style_path() -> PathBuf {
+    PathBuf::from(".").join("settings").join("stage.json")
+}
+pub fn load_speaking_style(mut style: ResMut<SpeakingStyle>) -> Result<(), BevyError> {
+    *style = read_settings(&speaking_style_path())?;
+    Ok(())
+}
+
+// Who said the line on screen, and how long it's been typing out
+#[derive(SystemParam)]
+pub struct SpokenLine<'w, 's> {
+    say_messages: MessageReader<'w, 's, CharacterSayMessage>,
+    speaker: Local<'s, Option<String>>,
+    line_time: Local<'s, Stopwatch>,
+    time: Res<'w, Time>,
+    markup_query: Query<'w, 's, &'static MarkupText>,
+    visibility_query: Query<'w, 's, (&'static Object, &'static Visibility)>,
+}
+
+// Shows the speaker's `<emotion>speaking` sprite while their line types out, flapping
+//  between it and the plain one, and dims whoever else is on stage
+pub fn animate_speakers(
+    mut commands: Commands,
+    line: SpokenLine,
+    style: Res<SpeakingStyle>,
+    sprites: Res<CharactersResource>,
+
+    mut character_query: Query<(Entity, &CharacterConfig, &mut Sprite, Option<&Tweens>)>,
+) {
+    let SpokenLine { mut say_messages, mut speaker, mut line_time, time, markup_query, visibility_query } = line;
+    for msg in say_messages.read() {
+        *speaker = Some(msg.name.clone());
+        line_time.reset();
+    }
+    line_time.tick(time.delta());
+
+    // Nobody is talking while the textbox is away
+    let textbox_visible = visibility_query.iter()
+        .any(|(obj, visibility)| obj.id == "_textbox_background" && *visibility != Visibility::Hidden);
+    let speaker = speaker.as_deref().filter(|_| textbox_visible);
+    let typing = markup_query.single().is_ok_and(|message| !message.is_revealed());
+    let speaker_on_stage = character_query.iter().any(|(_, config, _, _)| Some(config.name.as_str()) == speaker);
+
+    for (entity, config, mut sprite, tweens) in character_query.iter_mut() {
+        let speaking = Some(config.name.as_str()) == speaker;
+        let key = |emotion: String| SpriteKey {
+            character: config.name.clone(),
+            outfit: config.outfit.clone(),
+            emotion,
+        };
+        // Characters without speaking sprites keep still
+        let mouth_open = speaking && typing && style.mouth_open(line_time.elapsed_secs());
+        let image = mouth_open
+            .then(|| sprites.0.get(&key(format!("{}{}", config.emotion, SPEAKING_SUFFIX))))
+            .flatten()
+            .or_else(|| sprites.0.get(&key(config.emotion.clone())));
+        if let Some(image) = image && sprite.image != *image {
+            sprite.image = image.clone();
+        }
+
+        // A tint, so fades keep the alpha to themselves
+        let brightness = if speaker_on_stage && !speaking { style.listener_brightness } else { 1. };
+        let tint = Color::srgb(brightness, brightness, brightness);
+        let heading_to = match tweens.and_then(|tweens| tweens.target(&TweenTarget::Tint(tint))) {
+            Some(TweenTarget::Tint(heading_to)) => *heading_to,
+            _ => sprite.color.with_alpha(1.),
+        };
+        if heading_to != tint {
+            commands.entity(entity).tween(Tween::new(TweenTarget::Tint(tint), DIM_DURATION));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::character::CharacterConfig;
+use crate::display::DESIGN_RESOLUTION;
+use crate::tween::{Tween, TweenExt, TweenTarget, Tweens};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Height and size every character stands at
+const STAGE_Y: f32 = -40.;
+const STAGE_SCALE: f32 = 0.75;
+// Characters in front are drawn this much closer, so later arrivals overlap earlier ones
+const STAGE_Z: f32 = 1.;
+const Z_STEP: f32 = 0.01;
+// How long characters take to make room when someone enters or leaves
+pub const RELAYOUT_DURATION: f32 = 0.3;
+
+/* Custom types */
+// Where a character stands, as a fraction of the screen's width from its left edge
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StageSlot {
+    Left,
+    Center,
+    Right,
+    At(f32),
+}
+impl StageSlot {
+    pub fn fraction(&self) -> f32 {
+        match self {
+            StageSlot::Left => 0.25,
+            StageSlot::Center => 0.5,
+            StageSlot::Right => 0.75,
+            StageSlot::At(x) => *x,
+        }
+    }
+
+    // In the world, with the origin at the screen's center
+    pub fn x(&self) -> f32 {
+        fraction_to_x(self.fraction())
+    }
+}
+fn fraction_to_x(fraction: f32) -> f32 {
+    (fraction - 0.5) * DESIGN_RESOLUTION.x
+}
+
+/* Components */
+// Characters without a slot share the stage evenly with each other
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct StagePlacement {
+    pub slot: Option<StageSlot>,
+    // When they came on stage, which decides who is drawn in front
+    pub order: u32,
+}
+// Stage x of each placement, in the order given: slotted characters stand where
+//  they were told, the rest are spread evenly in the order they arrived
+pub fn stage_layout(placements: &[StagePlacement]) -> Vec<f32> {
+    let mut unslotted: Vec<usize> = (0..placements.len())
+        .filter(|index| placements[*index].slot.is_none())
+        .collect();
+    unslotted.sort_by_key(|index| placements[*index].order);
+
+    let mut fractions: Vec<f32> = placements.iter()
+        .map(|placement| placement.slot.map_or(0.5, |slot| slot.fraction()))
+        .collect();
+    for (position, index) in unslotted.iter().enumerate() {
+        fractions[*index] = (position + 1) as f32 / (unslotted.len() + 1) as f32;
+    }
+    fractions.into_iter().map(fraction_to_x).collect()
+}
+
+pub fn stage_transform(x: f32, order: u32) -> Transform {
+    Transform::from_xyz(x, STAGE_Y, STAGE_Z + order as f32 * Z_STEP)
+        .with_scale(Vec3::new(STAGE_SCALE, STAGE_SCALE, 1.))
+}
+
+// Everyone on stage, with any walk they're already on and whether they just arrived
+type StagedCharacter = (
+    Entity,
+    &'static StagePlacement,
+    &'static mut Transform,
+    Option<&'static Tweens>,
+    Ref<'static, CharacterConfig>,
+);
+
+// Lays the stage out again whenever someone arrives, leaves or changes slot.
+//  Newcomers appear in place, everyone else walks over
+pub fn layout_stage(
+    mut commands: Commands,
+    changed_query: Query<(), (With<CharacterConfig>, Changed<StagePlacement>)>,
+    mut removed: RemovedComponents<StagePlacement>,
+    mut character_query: Query<StagedCharacter>,
+) {
+    if changed_query.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+
+    let placements: Vec<StagePlacement> = character_query.iter().map(|(_, placement, _, _, _)| *placement).collect();
+    let targets = stage_layout(&placements);
+    for ((entity, placement, mut transform, tweens, config), x) in character_query.iter_mut().zip(targets) {
+        if config.is_added() {
+            *transform = stage_transform(x, placement.order);
+            continue;
+        }
+        transform.translation.z = STAGE_Z + placement.order as f32 * Z_STEP;
+        let heading_to = match tweens.and_then(|tweens| tweens.target(&TweenTarget::Translation(Vec3::ZERO))) {
+            Some(TweenTarget::Translation(translation)) => translation.x,
+            _ => transform.translation.x,
+        };
+        if heading_to != x {
+            commands.entity(entity).tween(Tween::new(TweenTarget::Translation(transform.translation.with_x(x)), RELAYOUT_DURATION));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::compiler::controller::ChoiceMadeMessage;
+use crate::input::{Action, ActionState};
+
+use bevy::prelude::*;
+
+const IDLE_COLOR: Color = Color::srgba(0.08, 0.08, 0.1, 0.85);
+const SELECTED_COLOR: Color = Color::srgba(0.35, 0.22, 0.45, 0.95);
+
+/* Messages */
+#[derive(Message)]
+pub struct ChoiceMessage {
+    pub prompt: Option<String>,
+    pub options: Vec<String>
+}
+
+/* Components */
+#[derive(Component)]
+pub struct ChoiceMenu {
+    pub prompt: Option<String>,
+    pub options: Vec<String>,
+    pub selected: usize,
+}
+#[derive(Component)]
+pub struct ChoiceButton {
+    pub index: usize
+}
+
+pub struct ChoiceController;
+impl Plugin for ChoiceController {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ChoiceMessage>()
+            .add_systems(Update, (spawn_choice_menu, navigate_choices, click_choices, highlight_choices).chain());
+    }
+}
+
+fn spawn_choice_menu(
+    mut commands: Commands,
+    mut choice_messages: MessageReader<ChoiceMessage>,
+    asset_server: Res<AssetServer>,
+) {
+    for msg in choice_messages.read() {
+        commands.spawn((
+            ChoiceMenu {
+                prompt: msg.prompt.clone(),
+                options: msg.options.clone(),
+                selected: 0,
+            },
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(14.),
+                ..default()
+            },
+            GlobalZIndex(10),
+        ))
+        .with_children(|parent| {
+            if let Some(prompt) = &msg.prompt {
+                parent.spawn((
+                    Text::new(prompt.clone()),
+                    TextFont {
+                        font: asset_server.load("fonts/BOLD.ttf"),
+                        font_size: 36.,
+                        ..default()
+                    },
+                    TextColor::WHITE,
+                    Node {
+                        margin: UiRect::bottom(Val::Px(10.)),
+                        ..default()
+                    },
+                ));
+            }
+            for (index, label) in msg.options.iter().enumerate() {
+                parent.spawn((
+                    Button,
+                    ChoiceButton { index },
+                    Node {
+                        width: Val::Px(600.),
+                        padding: UiRect::all(Val::Px(12.)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    BackgroundColor(IDLE_COLOR),
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        Text::new(label.clone()),
+                        TextFont {
+                            font: asset_server.load("fonts/ALLER.ttf"),
+                            font_size: 28.,
+                            ..default()
+                        },
+                        TextColor::WHITE,
+                    ));
+                });
+            }
+        });
+        info!("[ Showing choice with {} options ]", msg.options.len());
+    }
+}
+fn choose(
+    commands: &mut Commands,
+    choice_made_message: &mut MessageWriter<ChoiceMadeMessage>,
+    menu_entity: Entity,
+    index: usize,
+) {
+    choice_made_message.write(ChoiceMadeMessage { index });
+    commands.entity(menu_entity).despawn();
+}
+fn navigate_choices(
+    mut commands: Commands,
+    mut menu_query: Query<(Entity, &mut ChoiceMenu)>,
+    mut choice_made_message: MessageWriter<ChoiceMadeMessage>,
+    actions: Res<ActionState>,
+) {
+    for (menu_entity, mut menu) in menu_query.iter_mut() {
+        let count = menu.options.len();
+        if count == 0 {
+            continue;
+        }
+
+        if actions.triggered(Action::MenuDown) {
+            menu.selected = (menu.selected + 1) % count;
+        }
+        if actions.triggered(Action::MenuUp) {
+            menu.selected = (menu.selected + count - 1) % count;
+        }
+
+        if actions.just_pressed(Action::MenuConfirm) {
+            choose(&mut commands, &mut choice_made_message, menu_entity, menu.selected);
+        }
+    }
+}
+fn click_choices(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &ChoiceButton), Changed<Interaction>>,
+    mut menu_query: Query<(Entity, &mut ChoiceMenu)>,
+    mut choice_made_message: MessageWriter<ChoiceMadeMessage>,
+) {
+    let Ok((menu_entity, mut menu)) = menu_query.single_mut() else {
+        return;
+    };
+
+    for (interaction, button) in interaction_query.iter() {
+        match interaction {
+            Interaction::Pressed => {
+                choose(&mut commands, &mut choice_made_message, menu_entity, button.index);
+                return;
+            },
+            Interaction::Hovered => menu.selected = button.index,
+            Interaction::None => {}
+        }
+    }
+}
+fn highlight_choices(
+    menu_query: Query<&ChoiceMenu>,
+    mut button_query: Query<(&ChoiceButton, &mut BackgroundColor)>,
+) {
+    let Ok(menu) = menu_query.single() else {
+        return;
+    };
+
+    for (button, mut background) in button_query.iter_mut() {
+        background.0 = if button.index == menu.selected { SELECTED_COLOR } else { IDLE_COLOR };
+    }
+}
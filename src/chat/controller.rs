@@ -1,9 +1,9 @@
-use crate::{compiler::controller::{Controller, ControllerReadyMessage, TriggerControllersMessage}, Object, VisualNovelState};
+use crate::{character::Configs, chat::history::HistoryOverlay, chat::markup::{animate_glyphs, place_ruby, render_markup, MarkupText, RichText}, chat::typewriter::{load_typewriter, Typewriter}, chat::theme::{apply_theme, style_speaker, ChatTheme, ChatThemeLoader, HandleToChatTheme, DEFAULT_THEME}, compiler::controller::{Controller, ControllerReadyMessage, ScriptSystems, TriggerControllersMessage}, display::Letterbox, input::{Action, ActionState}, Object, VisualNovelState};
 
 use std::collections::HashMap;
 
 use anyhow::Context;
-use bevy::{asset::{LoadState, LoadedFolder}, color::palettes::css::RED, prelude::*, sprite::Anchor, text::{LineBreak, TextBounds}, time::Stopwatch, window::PrimaryWindow};
+use bevy::{asset::{LoadState, LoadedFolder}, prelude::*, sprite::{update_text2d_layout, Anchor}, text::TextBounds, time::Stopwatch, transform::TransformSystems, window::PrimaryWindow};
 
 /* Messages */
 #[derive(Message)]
@@ -11,6 +11,9 @@ pub struct CharacterSayMessage {
     pub name: String,
     pub message: String
 }
+// Moves the textbox on as if it was clicked
+#[derive(Message)]
+pub struct ChatAdvanceMessage;
 #[derive(Message)]
 pub struct GUIChangeMessage {
     pub gui_id: String,
@@ -27,6 +30,9 @@ enum ChatControllerState {
 }
 
 /* Components */
+// Roots of everything the hide UI action hides
+#[derive(Component)]
+pub struct HideableUi;
 #[derive(Component)]
 pub struct GUIScrollText {
     pub message: String
@@ -34,7 +40,7 @@ pub struct GUIScrollText {
 
 /* Resources */
 #[derive(Resource)]
-pub struct ChatScrollStopwatch(Stopwatch);
+pub struct ChatScrollStopwatch(pub Stopwatch);
 #[derive(Resource)]
 struct HandleToGuiFolder(Handle<LoadedFolder>);
 
@@ -68,79 +74,45 @@ impl TextBundle {
             visibility: Visibility::default()
         }
     }
-
-    fn with_font(self, font: TextFont) -> Self {
-        Self {
-            font,
-            ..self
-        }
-    }
-
-    fn with_layout(self, layout: TextLayout) -> Self {
-        Self {
-            layout,
-            ..self
-        }
-    }
-
-    fn with_color(self, color: TextColor) -> Self {
-        Self {
-            color,
-            ..self
-        }
-    }
-
-    fn with_anchor(self, anchor: Anchor) -> Self {
-        Self {
-            anchor,
-            ..self
-        }
-    }
-
-    fn with_transform(self, transform: Transform) -> Self {
-        Self {
-            transform,
-            ..self
-        }
-    }
-
-    fn with_bounds(self, bounds: TextBounds) -> Self {
-        Self {
-            bounds,
-            ..self
-        }
-    }
-
-    fn with_visibility(self, visibility: Visibility) -> Self {
-        Self {
-            visibility,
-            ..self
-        }
-    }
 }
 
 pub struct ChatController;
 impl Plugin for ChatController {
     fn build(&self, app: &mut App){
         app.insert_resource(ChatScrollStopwatch(Stopwatch::new()))
+            .init_resource::<Typewriter>()
+            .add_systems(Startup, load_typewriter)
             .init_state::<ChatControllerState>()
-            .add_systems(OnEnter(ChatControllerState::Loading), import_gui_sprites)
+            .init_asset::<ChatTheme>()
+            .init_asset_loader::<ChatThemeLoader>()
+            .add_systems(OnEnter(ChatControllerState::Loading), (import_gui_sprites, import_theme))
             .add_systems(Update, setup.run_if(in_state(ChatControllerState::Loading)))
             .add_message::<CharacterSayMessage>()
             .add_message::<GUIChangeMessage>()
+            .add_message::<ChatAdvanceMessage>()
             .add_systems(Update, wait_trigger.run_if(in_state(ChatControllerState::Idle)))
             .add_systems(OnEnter(ChatControllerState::Running), spawn_chatbox)
-            .add_systems(Update, (update_chatbox, update_gui).run_if(in_state(ChatControllerState::Running)));
+            .add_systems(Update, (click_textbox, update_chatbox, toggle_hidden_ui, render_markup).chain().after(ScriptSystems).run_if(in_state(ChatControllerState::Running)))
+            .add_systems(PostUpdate, (place_ruby, animate_glyphs).after(update_text2d_layout).before(TransformSystems::Propagate))
+            .add_systems(Update, (update_gui.after(ScriptSystems), (apply_theme, style_speaker).chain()).run_if(in_state(ChatControllerState::Running)));
     }
 }
 fn setup(
     asset_server: Res<AssetServer>,
     loaded_folders: Res<Assets<LoadedFolder>>,
     folder_handle: Res<HandleToGuiFolder>,
+    theme_handle: Res<HandleToChatTheme>,
     mut game_state: ResMut<VisualNovelState>,
     mut controller_state: ResMut<NextState<ChatControllerState>>,
     mut msg_writer: MessageWriter<ControllerReadyMessage>,
 ) -> Result<(), BevyError> {
+    // The chatbox is laid out by the theme, so it has to be there first
+    match asset_server.get_load_state(theme_handle.0.id()) {
+        Some(LoadState::Loaded) => {},
+        Some(LoadState::Failed(e)) => return Err(anyhow::anyhow!("Error loading chat theme: {}", e).into()),
+        _ => return Ok(()),
+    }
+
     let mut gui_sprites = HashMap::<String, Handle<Image>>::new();
     if let Some(state) = asset_server.get_load_state(folder_handle.0.id()) {
         match state {
@@ -169,19 +141,30 @@ fn setup(
     }
     Ok(())
 }
+fn import_theme(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(HandleToChatTheme(asset_server.load(DEFAULT_THEME)));
+}
 fn import_gui_sprites(mut commands: Commands, asset_server: Res<AssetServer> ){
     let loaded_folder = asset_server.load_folder("gui");
     commands.insert_resource(HandleToGuiFolder(loaded_folder));
 }
-fn spawn_chatbox(mut commands: Commands, asset_server: Res<AssetServer>){
-    // Spawn Backplate + Nameplate
+// Layout, fonts and colors come from the chat theme, see `apply_theme`
+fn spawn_chatbox(mut commands: Commands){
+    // Spawn Backplate + Nameplate, under a root the hide UI action can toggle
+    let root = commands.spawn((
+        HideableUi,
+        Transform::default(),
+        Visibility::Inherited,
+    ))
+    .id();
     commands.spawn((
         Object {
             id: String::from("_textbox_background")
         },
         Visibility::Hidden,
         Sprite::default(),
-        Transform::from_xyz(0., -275., 2.),
+        Transform::default(),
+        ChildOf(root),
     ))
     .with_children(|parent| {
         parent.spawn((
@@ -190,7 +173,7 @@ fn spawn_chatbox(mut commands: Commands, asset_server: Res<AssetServer>){
             },
             Visibility::Inherited,
             Sprite::default(),
-            Transform::from_xyz(-270., 105., 2.).with_scale( Vec3 { x: 0.75, y: 0.75, z: 2. } ),
+            Transform::default(),
         ));
         parent.spawn(
             TextBundle::new(
@@ -199,29 +182,16 @@ fn spawn_chatbox(mut commands: Commands, asset_server: Res<AssetServer>){
                 },
                 "UNFILLED"
             )
-            .with_font(TextFont {
-                           font: asset_server.load("fonts/ALLER.ttf"),
-                           font_size: 40.0,
-                           ..default()
-                       })
-            .with_anchor(Anchor::TOP_LEFT)
-            .with_transform(Transform::from_xyz(-305., 126., 3.))
         );
-        parent.spawn(
+        parent.spawn((
             TextBundle::new(
                 Object {
                     id: String::from("_message_text")
                 },
-                "UNFILLED"
-            )
-            .with_font(TextFont {
-                           font: asset_server.load("fonts/BOLDITALIC.ttf"),
-                           font_size: 27.0,
-                           ..default()
-                       })
-            .with_anchor(Anchor::TOP_LEFT)
-            .with_transform(Transform::from_xyz(-350., 62., 3.))
-            .with_bounds(TextBounds { width: Some(700.), height: Some(107.) }));
+                ""
+            ),
+            MarkupText::default(),
+        ));
     });
 
     commands.spawn(
@@ -231,48 +201,80 @@ fn spawn_chatbox(mut commands: Commands, asset_server: Res<AssetServer>){
             },
             "",
         )
-        .with_font(TextFont {
-                       font: asset_server.load("fonts/BOLD.ttf"),
-                       font_size: 50.,
-                       ..default()
-                   })
-        .with_anchor(Anchor::TOP_CENTER)
-        .with_layout(TextLayout {
-                         justify: Justify::Center,
-                         linebreak: LineBreak::WordBoundary,
-                     })
-        .with_color(TextColor(Color::Srgba(RED)))
-        .with_transform(Transform::from_xyz(0., 302., 3.))
-        .with_visibility(Visibility::Visible)
-        .with_bounds(TextBounds { width: Some(700.), height: None })
     );
 }
+// Whether design-space `point` is on a sprite of `size` pixels, drawn centered
+//  at `transform`, where the theme placed it
+pub fn sprite_contains(transform: &GlobalTransform, size: Vec2, point: Vec2) -> bool {
+    let (scale, _, translation) = transform.to_scale_rotation_translation();
+    Rect::from_center_size(translation.truncate(), size * scale.truncate()).contains(point)
+}
+// Advancing with the mouse only counts on the textbox, so clicks elsewhere
+//  are free for the rest of the screen
+fn click_textbox(
+    actions: Res<ActionState>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    textbox_query: Query<(&Object, &Sprite, &GlobalTransform)>,
+    images: Res<Assets<Image>>,
+    interaction_query: Query<&Interaction>,
+    mut advance_message: MessageWriter<ChatAdvanceMessage>,
+) {
+    if !actions.clicked(Action::Advance) {
+        return;
+    }
+    // A click on a button is meant for that button
+    if interaction_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        return;
+    }
+    let Ok(window) = window.single() else {
+        return;
+    };
+    // Clicks on the bars around the letterboxed view miss everything
+    let Some(point) = window.physical_cursor_position()
+        .and_then(|cursor| Letterbox::fit(window.physical_size().as_vec2()).to_design(cursor)) else {
+        return;
+    };
+
+    for (obj, sprite, transform) in textbox_query.iter() {
+        if obj.id != "_textbox_background" {
+            continue;
+        }
+        let Some(size) = sprite.custom_size.or_else(|| images.get(&sprite.image).map(|image| image.size_f32())) else {
+            continue;
+        };
+        if sprite_contains(transform, size, point) {
+            advance_message.write(ChatAdvanceMessage);
+        }
+    }
+}
 fn update_chatbox(
     mut event_message: MessageReader<CharacterSayMessage>,
+    mut advance_message: MessageReader<ChatAdvanceMessage>,
     mut visibility_query: Query<(&mut Visibility, &Object)>,
     mut text_object_query: Query<(&mut Text2d, &mut GUIScrollText, &Object)>,
+    mut markup_query: Query<&mut MarkupText>,
+    typewriter: Res<Typewriter>,
+    configs: Option<Res<Configs>>,
     mut scroll_stopwatch: ResMut<ChatScrollStopwatch>,
+    history_query: Query<(), With<HistoryOverlay>>,
 
     mut game_state: ResMut<VisualNovelState>,
 
     time: Res<Time>,
-    window: Query<&Window, With<PrimaryWindow>>,
-    buttons: Res<ButtonInput<MouseButton>>,
+    actions: Res<ActionState>,
+    interaction_query: Query<&Interaction>,
+    hideable_query: Query<&Visibility, (With<HideableUi>, Without<Object>)>,
 ) -> Result<(), BevyError> {
     /* QUICK USE VARIABLES */
     let mut name_text_option: Option<&mut Text2d> = None;
     let mut info_text_option: Option<&mut Text2d> = None;
-    let mut message_text_option: Option<&mut Text2d> = None;
     let mut message_scroll_text_obj_option: Option<&mut GUIScrollText> = None;
     
     for (text_literal, scroll_text_obj, text_obj) in text_object_query.iter_mut() {
         match text_obj.id.as_str() {
             "_name_text" => name_text_option = Some(text_literal.into_inner()),
             "_info_text" => info_text_option = Some(text_literal.into_inner()),
-            "_message_text" => {
-                message_text_option = Some(text_literal.into_inner());
-                message_scroll_text_obj_option = Some(scroll_text_obj.into_inner());
-            },
+            "_message_text" => message_scroll_text_obj_option = Some(scroll_text_obj.into_inner()),
             _ => {}
         }
     }
@@ -281,10 +283,10 @@ fn update_chatbox(
         .context("Missing GUI text object with ID '_name_text'")?;
     let info_text = info_text_option
         .context("Missing GUI text object with ID '_info_text'")?;
-    let message_text = message_text_option
-        .context("Missing GUI text object with ID '_message_text'")?;
     let message_scroll_text_obj = message_scroll_text_obj_option
         .context("Missing GUI scroll text object with ID '_message_text'")?;
+    let mut message_markup = markup_query.single_mut()
+        .context("Missing GUI markup text object with ID '_message_text'")?;
 
     let mut textbox_visibility_option: Option<&mut Visibility> = None;
     for (visibility_literal, textbox_obj) in visibility_query.iter_mut() {
@@ -296,6 +298,8 @@ fn update_chatbox(
     let textbox_visibility = textbox_visibility_option
         .context("Missing GUI object with ID '_textbox_background'")?;
 
+    let advance_requested = advance_message.read().count() > 0;
+
     // Tick clock
     let to_tick = if time.delta_secs() > 1. { std::time::Duration::from_secs_f32(0.) } else { time.delta() };
     scroll_stopwatch.0.tick(to_tick);
@@ -304,19 +308,25 @@ fn update_chatbox(
     for ev in event_message.read() {
         game_state.blocking = true;
 
-        // Make the parent textbox visible
-        *textbox_visibility = Visibility::Visible;
+        // Show the textbox, unless the whole UI is hidden
+        *textbox_visibility = Visibility::Inherited;
 
         // Reset the scrolling timer
         scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(0.));
 
         // Update the name
-        let name = if ev.name == "[_PLAYERNAME_]" { game_state.playername.clone() } else { ev.name.clone() };
-        name_text.0 = name;
+        name_text.0 = game_state.speaker_name(&ev.name);
 
         println!("MESSAGE {}", ev.message);
 
         message_scroll_text_obj.message = ev.message.clone();
+        let markup = RichText::parse(&ev.message)
+            .with_context(|| game_state.diagnostic(format!("Invalid markup in '{}'", ev.message)))?;
+        // Speakers can type faster or slower than everyone else
+        let character_delay = configs.as_ref()
+            .and_then(|configs| configs.0.get(&ev.name))
+            .and_then(|config| config.character_delay);
+        message_markup.set(markup, &typewriter.with_character_delay(character_delay));
     }
 
     // If the textbox is hidden, ignore the next section dedicated to updating it
@@ -324,47 +334,54 @@ fn update_chatbox(
         return Ok(());
     }
 
-    // Take the original string from the message object
-    let mut original_string: String = message_scroll_text_obj.message.clone();
-
-    // Get the section of the string according to the elapsed time
-    let length: u32 = (scroll_stopwatch.0.elapsed_secs() * 50.) as u32;
+    // Type out as much of the line as the elapsed time allows; `render_markup` shows it
+    message_markup.reveal(scroll_stopwatch.0.elapsed_secs());
 
-    // Return the section and apply it to the text object
-    original_string.truncate(length as usize);
-    message_text.0 = original_string;
+    // Input belongs to the history overlay while it's open, and to
+    //  `toggle_hidden_ui` while the UI is hidden
+    if !history_query.is_empty() || hideable_query.iter().any(|visibility| *visibility == Visibility::Hidden) {
+        return Ok(());
+    }
 
-    let window = window.single()
-        .context("Failed to query for primary window")?;
-    
-    if let Some(position) = window.cursor_position() {
-        let resolution = &window.resolution;
-        let textbox_bounds: [f32; 4] = [
-            (resolution.width() / 2.) - (796. / 2.),
-            (resolution.width() / 2.) + (796. / 2.),
-            (resolution.height() / 2.) - (155. / 2.) + (275.),
-            (resolution.height() / 2.) + (155. / 2.) + (275.),
-        ];
-        if ( position.x > textbox_bounds[0] && position.x < textbox_bounds[1] ) && ( position.y > textbox_bounds[2] && position.y < textbox_bounds[3] ) && buttons.just_pressed(MouseButton::Left) {
-            if length < message_scroll_text_obj.message.len() as u32 {
-                // Skip message scrolling
-                scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(100000000.));
-                return Ok(());
-            }
-            println!("[ Player finished message ]");
-            info_text.0 = String::from("");
+    // A click on a button is meant for that button
+    let clicked_button = interaction_query.iter().any(|interaction| *interaction == Interaction::Pressed);
+    // Clicks come from `click_textbox`, which checks they're on the textbox
+    let advance = actions.triggered_without_clicking(Action::Advance) && !clicked_button;
+    if advance || advance_requested {
+        if !message_markup.is_revealed() {
+            // Skip message scrolling
+            scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(100000000.));
+            return Ok(());
+        }
+        println!("[ Player finished message ]");
+        info_text.0 = String::from("");
 
-            // Hide textbox parent object
-            *textbox_visibility = Visibility::Hidden;
+        // Hide textbox parent object
+        *textbox_visibility = Visibility::Hidden;
 
-            // Allow transitions to be run again
-            game_state.blocking = false;
-        }
+        // Allow transitions to be run again
+        game_state.blocking = false;
     }
     
     Ok(())
 }
 
+fn toggle_hidden_ui(
+    actions: Res<ActionState>,
+    mut hideable_query: Query<&mut Visibility, (With<HideableUi>, Without<Object>)>,
+) {
+    let hidden = hideable_query.iter().any(|visibility| *visibility == Visibility::Hidden);
+    // Any way of moving on brings the UI back instead
+    let toggle = actions.triggered(Action::HideUi)
+        || (hidden && (actions.triggered(Action::Advance) || actions.triggered(Action::Menu)));
+    if !toggle {
+        return;
+    }
+
+    for mut visibility in hideable_query.iter_mut() {
+        *visibility = if hidden { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
 fn wait_trigger(
     mut msg_reader: MessageReader<TriggerControllersMessage>,
     mut controller_state: ResMut<NextState<ChatControllerState>>,
@@ -383,7 +400,7 @@ fn update_gui(
         for (gui_obj, mut current_sprite) in gui_query.iter_mut() {
             if gui_obj.id == ev.gui_id {
                 let gui_sprite = game_state.gui_sprites.get(&ev.sprite_id)
-                    .with_context(|| format!("GUI asset '{}' does not exist", ev.sprite_id))?;
+                    .with_context(|| game_state.diagnostic(format!("GUI asset '{}' does not exist", ev.sprite_id)))?;
                 current_sprite.image = gui_sprite.clone();
                 println!("[ Set GUI asset '{}' to '{}']", ev.gui_id, ev.sprite_id);
             }
@@ -0,0 +1,193 @@
+use crate::audio::{AudioChangeMessage, AudioOperation};
+use crate::chat::markup::RichText;
+use crate::compiler::controller::SabiState;
+use crate::input::{Action, ActionState};
+use crate::VisualNovelState;
+
+use std::collections::VecDeque;
+
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use serde::{Deserialize, Serialize};
+
+// Oldest lines are dropped past this, so saves and the overlay stay small
+pub const HISTORY_LIMIT: usize = 200;
+const LINE_HEIGHT: f32 = 28.;
+
+const IDLE_COLOR: Color = Color::NONE;
+const HOVERED_COLOR: Color = Color::srgba(0.35, 0.22, 0.45, 0.6);
+
+/* Custom types */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub name: String,
+    pub message: String,
+    pub voice: Option<String>,
+}
+
+// Every line said so far, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct History(VecDeque<HistoryEntry>);
+impl History {
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.0.len() == HISTORY_LIMIT {
+            self.0.pop_front();
+        }
+        self.0.push_back(entry);
+    }
+
+    pub fn entries(&self) -> &VecDeque<HistoryEntry> {
+        &self.0
+    }
+}
+
+/* Components */
+#[derive(Component)]
+pub struct HistoryOverlay;
+#[derive(Component)]
+struct HistoryList;
+#[derive(Component)]
+struct HistoryVoice(String);
+
+pub struct HistoryController;
+impl Plugin for HistoryController {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (scroll_history, toggle_history, replay_voices).chain().run_if(in_state(SabiState::Running)));
+    }
+}
+
+fn spawn_history(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    game_state: &VisualNovelState,
+) {
+    commands.spawn((
+        HistoryOverlay,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.03, 0.03, 0.05, 0.9)),
+        // Keeps clicks off the choices and textbox underneath
+        FocusPolicy::Block,
+        GlobalZIndex(50),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            HistoryList,
+            Node {
+                width: Val::Px(900.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.),
+                padding: UiRect::axes(Val::Px(12.), Val::Px(40.)),
+                overflow: Overflow::scroll_y(),
+                ..default()
+            },
+            // Start at the most recent line; layout clamps this to the end
+            ScrollPosition(Vec2::new(0., f32::MAX)),
+        ))
+        .with_children(|list| {
+            for entry in game_state.history.entries() {
+                let name = game_state.speaker_name(&entry.name);
+                // The log is plain text, so markup is dropped rather than shown
+                let message = RichText::parse(&entry.message)
+                    .map(|markup| markup.plain())
+                    .unwrap_or_else(|_| entry.message.clone());
+                let mut row = list.spawn((
+                    Button,
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(8.)),
+                        flex_shrink: 0.,
+                        ..default()
+                    },
+                    BackgroundColor(IDLE_COLOR),
+                ));
+                if let Some(voice) = &entry.voice {
+                    row.insert(HistoryVoice(voice.clone()));
+                }
+                row.with_children(|row| {
+                    row.spawn((
+                        Text::new(name),
+                        TextFont {
+                            font: asset_server.load("fonts/BOLD.ttf"),
+                            font_size: 24.,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(0.85, 0.75, 1.)),
+                    ));
+                    row.spawn((
+                        Text::new(message),
+                        TextFont {
+                            font: asset_server.load("fonts/ALLER.ttf"),
+                            font_size: 22.,
+                            ..default()
+                        },
+                        TextColor::WHITE,
+                    ));
+                });
+            }
+        });
+    });
+}
+fn toggle_history(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_state: Res<VisualNovelState>,
+    actions: Res<ActionState>,
+    overlay_query: Query<Entity, With<HistoryOverlay>>,
+) {
+    if let Ok(overlay) = overlay_query.single() {
+        // The wheel scrolls the open log rather than closing it
+        if actions.just_pressed(Action::Backlog) || actions.triggered(Action::Menu) {
+            commands.entity(overlay).despawn();
+        }
+        return;
+    }
+
+    if actions.triggered(Action::Backlog) {
+        spawn_history(&mut commands, &asset_server, &game_state);
+    }
+}
+fn scroll_history(
+    mut wheel_messages: MessageReader<MouseWheel>,
+    mut list_query: Query<(&mut ScrollPosition, &ComputedNode), With<HistoryList>>,
+) {
+    let Ok((mut scroll_position, node)) = list_query.single_mut() else {
+        wheel_messages.clear();
+        return;
+    };
+
+    let max_offset = ((node.content_size().y - node.size().y) * node.inverse_scale_factor()).max(0.);
+    for wheel in wheel_messages.read() {
+        let delta = match wheel.unit {
+            MouseScrollUnit::Line => wheel.y * LINE_HEIGHT,
+            MouseScrollUnit::Pixel => wheel.y,
+        };
+        scroll_position.y = (scroll_position.y.min(max_offset) - delta).clamp(0., max_offset);
+    }
+}
+fn replay_voices(
+    mut interaction_query: Query<(&Interaction, &HistoryVoice, &mut BackgroundColor), Changed<Interaction>>,
+    mut audio_change_message: MessageWriter<AudioChangeMessage>,
+) {
+    // Only voiced lines react, so the player can tell which ones can be replayed
+    for (interaction, voice, mut background) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Pressed => {
+                audio_change_message.write(AudioChangeMessage {
+                    operation: AudioOperation::PlayVoice(Some(voice.0.clone())),
+                });
+                background.0 = HOVERED_COLOR;
+            },
+            Interaction::Hovered => background.0 = HOVERED_COLOR,
+            Interaction::None => background.0 = IDLE_COLOR,
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use crate::chat::typewriter::Typewriter;
+
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::text::{ComputedTextBlock, TextBounds, TextLayoutInfo};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Ruby sits this much smaller than its base text, this far above it
+const RUBY_SCALE: f32 = 0.5;
+const RUBY_GAP: f32 = 2.;
+
+const SHAKE_DISTANCE: f32 = 1.5;
+const SHAKE_RATE: f32 = 30.;
+const WAVE_HEIGHT: f32 = 4.;
+const WAVE_SPEED: f32 = 6.;
+const WAVE_SPACING: f32 = 0.5;
+
+/* Custom types */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEffect {
+    Shake,
+    Wave,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub color: Option<Color>,
+    pub size: Option<f32>,
+    pub effect: Option<TextEffect>,
+    // Small text drawn over the span, like furigana
+    pub ruby: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledSpan {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+// Changes to how fast the line types out, before the grapheme at their index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pace {
+    Wait(f32),
+    // Characters per second, or back to the default with None
+    Speed(Option<f32>),
+}
+
+// A dialogue line with its markup parsed: `{b}`, `{i}`, `{color=#hex}`, `{size=N}`,
+//  `{ruby=text}`, `{shake}` and `{wave}` wrap text and close with `{/tag}`, while
+//  `{w=seconds}` pauses and `{speed=N}` types N characters per second until `{/speed}`.
+//  `{{` is a literal brace
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RichText {
+    pub spans: Vec<StyledSpan>,
+    pub pacing: Vec<(usize, Pace)>,
+}
+
+impl RichText {
+    pub fn parse(markup: &str) -> Result<Self> {
+        let mut rich = RichText::default();
+        let mut style = SpanStyle::default();
+        let mut speed: Option<f32> = None;
+        // Open tags, with the style and speed to go back to when they close
+        let mut open: Vec<(String, SpanStyle, Option<f32>)> = Vec::new();
+        let mut current = String::new();
+
+        let mut chars = markup.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '{' {
+                current.push(c);
+                continue;
+            }
+            if chars.next_if_eq(&'{').is_some() {
+                current.push('{');
+                continue;
+            }
+            // Pacing lands before the next grapheme shown
+            let count = rich.grapheme_count() + current.graphemes(true).count();
+
+            let mut tag = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => tag.push(c),
+                    None => bail!("Tag '{{{}' is never closed with '}}'", tag),
+                }
+            }
+            if style.ruby.is_some() && tag != "/ruby" {
+                bail!("Ruby text can't contain other tags, found '{{{}}}'", tag);
+            }
+
+            let (name, value) = match tag.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (tag.trim(), None),
+            };
+            if let Some(closing) = name.strip_prefix('/') {
+                if value.is_some() {
+                    bail!("Closing tag '{{{}}}' can't have a value", tag);
+                }
+                let Some((opening, previous_style, previous_speed)) = open.pop() else {
+                    bail!("'{{{}}}' closes a tag that was never opened", tag);
+                };
+                if opening != closing {
+                    bail!("'{{{}}}' closes '{{{}}}', which is still open", tag, opening);
+                }
+                if closing == "speed" {
+                    rich.pacing.push((count, Pace::Speed(previous_speed)));
+                } else {
+                    if closing == "ruby" && current.is_empty() {
+                        bail!("Ruby needs text to sit over");
+                    }
+                    rich.flush(&mut current, &style);
+                    style = previous_style;
+                }
+                speed = previous_speed;
+                continue;
+            }
+
+            if name == "w" {
+                let seconds = parse_positive(&tag, value)?;
+                rich.pacing.push((count, Pace::Wait(seconds)));
+                continue;
+            }
+
+            open.push((name.to_owned(), style.clone(), speed));
+            if name == "speed" {
+                speed = Some(parse_positive(&tag, value)?);
+                rich.pacing.push((count, Pace::Speed(speed)));
+                continue;
+            }
+            rich.flush(&mut current, &style);
+            match (name, value) {
+                ("b", None) => style.bold = true,
+                ("i", None) => style.italic = true,
+                ("shake", None) => style.effect = Some(TextEffect::Shake),
+                ("wave", None) => style.effect = Some(TextEffect::Wave),
+                ("size", Some(_)) => style.size = Some(parse_positive(&tag, value)?),
+                ("color", Some(hex)) => {
+                    let color = Srgba::hex(hex)
+                        .map_err(|_| anyhow::anyhow!("Invalid color '{}' in '{{{}}}'", hex, tag))?;
+                    style.color = Some(Color::Srgba(color));
+                },
+                ("ruby", Some(ruby)) => style.ruby = Some(ruby.to_owned()),
+                _ => bail!("Unknown tag '{{{}}}'", tag),
+            }
+        }
+        // Tags still open at the end of the line close there
+        rich.flush(&mut current, &style);
+        Ok(rich)
+    }
+
+    fn flush(&mut self, current: &mut String, style: &SpanStyle) {
+        if !current.is_empty() {
+            self.spans.push(StyledSpan {
+                text: std::mem::take(current),
+                style: style.clone(),
+            });
+        }
+    }
+
+    // The line without its markup, for places that can't show styles
+    pub fn plain(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    // User-perceived characters, so an emoji or accented letter types out whole
+    pub fn grapheme_count(&self) -> usize {
+        self.spans.iter().map(|span| span.text.graphemes(true).count()).sum()
+    }
+
+    // Seconds into the line at which each grapheme appears
+    pub fn reveal_times(&self, typewriter: &Typewriter) -> Vec<f32> {
+        let graphemes: Vec<&str> = self.spans.iter()
+            .flat_map(|span| span.text.graphemes(true))
+            .collect();
+        let mut times = Vec::with_capacity(graphemes.len());
+        let mut time = 0.;
+        let mut delay = typewriter.character_delay;
+        let mut pacing = self.pacing.iter().peekable();
+        for (index, grapheme) in graphemes.iter().enumerate() {
+            while let Some((_, pace)) = pacing.next_if(|(at, _)| *at == index) {
+                match pace {
+                    Pace::Wait(seconds) => time += seconds,
+                    Pace::Speed(cps) => delay = cps.map_or(typewriter.character_delay, f32::recip),
+                }
+            }
+            time += delay;
+            times.push(time);
+            if let Some(next) = graphemes.get(index + 1) {
+                time += typewriter.pause_between(grapheme, next);
+            }
+        }
+        times
+    }
+}
+fn parse_positive(tag: &str, value: Option<&str>) -> Result<f32> {
+    let value = value.with_context(|| format!("'{{{}}}' needs a value", tag))?;
+    match value.parse::<f32>() {
+        Ok(number) if number > 0. => Ok(number),
+        _ => bail!("'{}' in '{{{}}}' should be a positive number", value, tag),
+    }
+}
+
+// Up to `count` graphemes of the text
+fn grapheme_prefix(text: &str, count: usize) -> &str {
+    match text.grapheme_indices(true).nth(count) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/* Components */
+// Text shown through styled spans and typed out by `reveal`; the object's
+//  own text stays empty
+#[derive(Component, Default)]
+#[require(StyleFonts, GlyphOffsets)]
+pub struct MarkupText {
+    markup: RichText,
+    reveal_times: Vec<f32>,
+    revealed: usize,
+    // The spans no longer match the markup
+    rebuild: bool,
+}
+impl MarkupText {
+    pub fn set(&mut self, markup: RichText, typewriter: &Typewriter) {
+        self.reveal_times = markup.reveal_times(typewriter);
+        self.markup = markup;
+        self.revealed = 0;
+        self.rebuild = true;
+    }
+
+    // Shows every grapheme due `elapsed` seconds into the line
+    pub fn reveal(&mut self, elapsed: f32) {
+        self.revealed = self.reveal_times.partition_point(|time| *time <= elapsed);
+    }
+
+    pub fn markup(&self) -> &RichText {
+        &self.markup
+    }
+
+    pub fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.revealed >= self.reveal_times.len()
+    }
+}
+
+// Fonts for bold and italic markup; unset ones keep the object's own font
+#[derive(Component, Default)]
+pub struct StyleFonts {
+    pub bold: Option<Handle<Font>>,
+    pub italic: Option<Handle<Font>>,
+    pub bold_italic: Option<Handle<Font>>,
+}
+
+#[derive(Component)]
+pub struct MarkupSpan(usize);
+#[derive(Component)]
+pub struct RubyText {
+    span_index: usize,
+    base: Entity,
+}
+// How far each glyph was moved by its effect last frame
+#[derive(Component, Default)]
+pub struct GlyphOffsets(Vec<Vec2>);
+
+// Spans copy the object's font and color, so theme and speaker changes reach them
+fn span_font(style: &SpanStyle, base: &TextFont, fonts: &StyleFonts) -> TextFont {
+    let font = match (style.bold, style.italic) {
+        (true, true) => fonts.bold_italic.as_ref(),
+        (true, false) => fonts.bold.as_ref(),
+        (false, true) => fonts.italic.as_ref(),
+        (false, false) => None,
+    };
+    TextFont {
+        font: font.unwrap_or(&base.font).clone(),
+        font_size: style.size.unwrap_or(base.font_size),
+        ..base.clone()
+    }
+}
+
+// Ruby sits above its span, outside the markup's own text
+type RubyFilter = (Without<MarkupText>, Without<MarkupSpan>);
+
+pub fn render_markup(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut MarkupText, &TextFont, &TextColor, &StyleFonts)>,
+    mut span_query: Query<(&MarkupSpan, &ChildOf, &mut TextSpan, &mut TextFont, &mut TextColor), Without<MarkupText>>,
+    mut ruby_query: Query<(&RubyText, &ChildOf, &mut Text2d, &mut TextFont, &mut TextColor), RubyFilter>,
+) {
+    for (entity, mut markup_text, base_font, base_color, fonts) in text_query.iter_mut() {
+        if markup_text.rebuild {
+            markup_text.rebuild = false;
+            commands.entity(entity).despawn_related::<Children>();
+            for (index, span) in markup_text.markup.spans.iter().enumerate() {
+                let base = commands.spawn((MarkupSpan(index), TextSpan::default(), ChildOf(entity))).id();
+                if span.style.ruby.is_some() {
+                    commands.spawn((
+                        RubyText { span_index: index, base },
+                        Text2d::default(),
+                        Anchor::BOTTOM_CENTER,
+                        Transform::default(),
+                        ChildOf(entity),
+                    ));
+                }
+            }
+            // Filled in next frame, once they exist
+            continue;
+        }
+
+        let mut remaining = markup_text.revealed;
+        let mut shown = Vec::with_capacity(markup_text.markup.spans.len());
+        for span in &markup_text.markup.spans {
+            let text = grapheme_prefix(&span.text, remaining);
+            remaining -= text.graphemes(true).count();
+            shown.push(text);
+        }
+
+        for (span_index, parent, mut text, mut font, mut color) in span_query.iter_mut() {
+            let Some(span) = markup_text.markup.spans.get(span_index.0) else {
+                continue;
+            };
+            if parent.parent() != entity {
+                continue;
+            }
+            if text.0 != shown[span_index.0] {
+                text.0 = shown[span_index.0].to_owned();
+            }
+            font.set_if_neq(span_font(&span.style, base_font, fonts));
+            color.set_if_neq(TextColor(span.style.color.unwrap_or(base_color.0)));
+        }
+        for (ruby, parent, mut text, mut font, mut color) in ruby_query.iter_mut() {
+            let Some(span) = markup_text.markup.spans.get(ruby.span_index) else {
+                continue;
+            };
+            if parent.parent() != entity {
+                continue;
+            }
+            // Appears once its base starts typing out
+            let ruby_text = match (&span.style.ruby, shown[ruby.span_index].is_empty()) {
+                (Some(ruby_text), false) => ruby_text.clone(),
+                _ => String::new(),
+            };
+            if text.0 != ruby_text {
+                text.0 = ruby_text;
+            }
+            let mut ruby_font = span_font(&span.style, base_font, fonts);
+            ruby_font.font_size *= RUBY_SCALE;
+            font.set_if_neq(ruby_font);
+            color.set_if_neq(TextColor(span.style.color.unwrap_or(base_color.0)));
+        }
+    }
+}
+
+// Centers ruby over where its base was laid out
+pub fn place_ruby(
+    text_query: Query<(&TextLayoutInfo, &TextBounds, &Anchor), With<MarkupText>>,
+    mut ruby_query: Query<(&RubyText, &ChildOf, &mut Transform)>,
+) {
+    for (ruby, parent, mut transform) in ruby_query.iter_mut() {
+        let Ok((layout, bounds, anchor)) = text_query.get(parent.parent()) else {
+            continue;
+        };
+        let Some((_, rect)) = layout.section_rects.iter().find(|(section, _)| *section == ruby.base) else {
+            continue;
+        };
+        // Same placement Text2d uses for its glyphs, which are laid out top down
+        let size = Vec2::new(
+            bounds.width.unwrap_or(layout.size.x),
+            bounds.height.unwrap_or(layout.size.y),
+        );
+        let top_left = (Anchor::TOP_LEFT.0 - anchor.as_vec()) * size;
+        let position = top_left + Vec2::new(rect.center().x, -rect.min.y) / layout.scale_factor + Vec2::Y * RUBY_GAP;
+        transform.translation = position.extend(0.1);
+    }
+}
+
+// Moves the glyphs of shaking and waving spans after layout. The offsets are
+//  written past change detection so they don't trigger another layout
+pub fn animate_glyphs(
+    time: Res<Time>,
+    mut text_query: Query<(&mut TextLayoutInfo, &ComputedTextBlock, &MarkupText, &mut GlyphOffsets)>,
+    span_query: Query<&MarkupSpan>,
+) {
+    let elapsed = time.elapsed_secs();
+    for (mut layout, computed, markup_text, mut offsets) in text_query.iter_mut() {
+        let laid_out = layout.is_changed();
+        let layout = layout.bypass_change_detection();
+        if laid_out || offsets.0.len() != layout.glyphs.len() {
+            offsets.0 = vec![Vec2::ZERO; layout.glyphs.len()];
+        }
+
+        for (index, glyph) in layout.glyphs.iter_mut().enumerate() {
+            let effect = computed.entities().get(glyph.span_index)
+                .and_then(|section| span_query.get(section.entity).ok())
+                .and_then(|span| markup_text.markup.spans.get(span.0))
+                .and_then(|span| span.style.effect);
+            let offset = match effect {
+                Some(TextEffect::Shake) => {
+                    let step = (elapsed * SHAKE_RATE).floor();
+                    Vec2::new(noise(index as f32, step), noise(step, index as f32)) * SHAKE_DISTANCE
+                },
+                Some(TextEffect::Wave) => Vec2::Y * (elapsed * WAVE_SPEED - index as f32 * WAVE_SPACING).sin() * WAVE_HEIGHT,
+                None => Vec2::ZERO,
+            } * layout.scale_factor;
+            glyph.position += offset - offsets.0[index];
+            offsets.0[index] = offset;
+        }
+    }
+}
+// Cheap repeatable noise in -1..1
+fn noise(x: f32, y: f32) -> f32 {
+    ((x * 12.9898 + y * 78.233).sin() * 43758.547).fract()
+}
@@ -1,7 +1,30 @@
+pub mod choice;
 pub mod controller;
+pub mod history;
+pub mod markup;
+pub mod playback;
+pub mod theme;
+pub mod typewriter;
 
 pub use controller::ChatController;
 pub use controller::GUIScrollText;
 pub use controller::ChatScrollStopwatch;
+pub use controller::ChatAdvanceMessage;
 pub use controller::CharacterSayMessage;
-pub use controller::GUIChangeMessage;
\ No newline at end of file
+pub use controller::GUIChangeMessage;
+
+pub use choice::ChoiceController;
+pub use choice::ChoiceMenu;
+pub use choice::ChoiceMessage;
+
+pub use history::History;
+pub use history::HistoryController;
+pub use history::HistoryEntry;
+
+pub use markup::MarkupText;
+pub use markup::RichText;
+
+pub use playback::PlaybackController;
+pub use playback::PlaybackMode;
+
+pub use typewriter::Typewriter;
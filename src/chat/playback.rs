@@ -0,0 +1,225 @@
+use crate::chat::controller::{ChatAdvanceMessage, HideableUi};
+use crate::chat::markup::MarkupText;
+use crate::chat::history::HistoryOverlay;
+use crate::chat::ChoiceMenu;
+use crate::compiler::controller::SabiState;
+use crate::input::{Action, ActionState};
+use crate::{Object, VisualNovelState};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+
+// Auto mode waits this long after a line finishes, plus a little per character
+const AUTO_BASE_DELAY: f32 = 1.;
+const AUTO_DELAY_PER_CHARACTER: f32 = 0.03;
+
+const IDLE_COLOR: Color = Color::srgba(0.08, 0.08, 0.1, 0.75);
+const ACTIVE_COLOR: Color = Color::srgba(0.35, 0.22, 0.45, 0.95);
+
+/* Components */
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackButton {
+    Auto,
+    Skip,
+    SkipUnread,
+}
+#[derive(Component)]
+struct PlaybackIndicator;
+
+/* Resources */
+#[derive(Resource, Default)]
+pub struct PlaybackMode {
+    pub auto: bool,
+    pub skip: bool,
+    auto_wait: Stopwatch,
+}
+
+pub struct PlaybackController;
+impl Plugin for PlaybackController {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaybackMode>()
+            .add_systems(OnEnter(SabiState::Running), spawn_playback_buttons)
+            .add_systems(Update, (toggle_playback, click_playback_buttons, advance_playback, show_playback).chain().run_if(in_state(SabiState::Running)));
+    }
+}
+
+fn spawn_playback_buttons(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        HideableUi,
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.),
+            bottom: Val::Px(8.),
+            column_gap: Val::Px(8.),
+            ..default()
+        },
+        GlobalZIndex(5),
+    ))
+    .with_children(|parent| {
+        for (button, label) in [(PlaybackButton::Auto, "Auto"), (PlaybackButton::Skip, "Skip"), (PlaybackButton::SkipUnread, "Skip all")] {
+            parent.spawn((
+                Button,
+                button,
+                Node {
+                    width: Val::Px(70.),
+                    padding: UiRect::all(Val::Px(4.)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                BackgroundColor(IDLE_COLOR),
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    Text::new(label),
+                    TextFont {
+                        font: asset_server.load("fonts/ALLER.ttf"),
+                        font_size: 18.,
+                        ..default()
+                    },
+                    TextColor::WHITE,
+                ));
+            });
+        }
+    });
+
+    commands.spawn((
+        HideableUi,
+        PlaybackIndicator,
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/BOLD.ttf"),
+            font_size: 24.,
+            ..default()
+        },
+        TextColor::WHITE,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.),
+            right: Val::Px(20.),
+            ..default()
+        },
+        GlobalZIndex(5),
+    ));
+}
+fn toggle_playback(
+    actions: Res<ActionState>,
+    mut playback: ResMut<PlaybackMode>,
+) {
+    if actions.triggered(Action::Auto) {
+        playback.auto = !playback.auto;
+    }
+    if actions.triggered(Action::Skip) {
+        playback.skip = !playback.skip;
+    }
+}
+fn click_playback_buttons(
+    interaction_query: Query<(&Interaction, &PlaybackButton), Changed<Interaction>>,
+    mut playback: ResMut<PlaybackMode>,
+    mut game_state: ResMut<VisualNovelState>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            match button {
+                PlaybackButton::Auto => playback.auto = !playback.auto,
+                PlaybackButton::Skip => playback.skip = !playback.skip,
+                // Kept with the persistent data, so it applies to every playthrough
+                PlaybackButton::SkipUnread => {
+                    let skip_unread = !game_state.persistent.skip_unread;
+                    game_state.persistent.set_skip_unread(skip_unread);
+                },
+            }
+        }
+    }
+}
+
+// How long auto mode waits after a line of `characters` graphemes is fully shown
+pub fn auto_delay(characters: usize) -> f32 {
+    AUTO_BASE_DELAY + AUTO_DELAY_PER_CHARACTER * characters as f32
+}
+
+// Whether skip mode goes past a line, rather than stopping for the player to read it
+pub fn should_skip(line_read: bool, skip_unread: bool) -> bool {
+    line_read || skip_unread
+}
+// What's on screen that playback has to wait for
+#[derive(SystemParam)]
+pub struct ChatScreen<'w, 's> {
+    markup_query: Query<'w, 's, &'static MarkupText>,
+    visibility_query: Query<'w, 's, (&'static Object, &'static Visibility)>,
+    choice_query: Query<'w, 's, (), With<ChoiceMenu>>,
+    history_query: Query<'w, 's, (), With<HistoryOverlay>>,
+}
+
+pub fn advance_playback(
+    mut playback: ResMut<PlaybackMode>,
+    game_state: Res<VisualNovelState>,
+    time: Res<Time>,
+
+    screen: ChatScreen,
+    mut advance_message: MessageWriter<ChatAdvanceMessage>,
+) {
+    let ChatScreen { markup_query, visibility_query, choice_query, history_query } = screen;
+    // Skipping always stops for the player to make a choice
+    if !choice_query.is_empty() {
+        playback.skip = false;
+    }
+    if !history_query.is_empty() {
+        return;
+    }
+
+    let textbox_visible = visibility_query.iter()
+        .any(|(obj, visibility)| obj.id == "_textbox_background" && *visibility != Visibility::Hidden);
+    let Ok(message) = markup_query.single() else {
+        return;
+    };
+    if !textbox_visible {
+        playback.auto_wait.reset();
+        return;
+    }
+
+    if playback.skip {
+        if should_skip(game_state.line_read, game_state.persistent().skip_unread) {
+            advance_message.write(ChatAdvanceMessage);
+            return;
+        }
+        playback.skip = false;
+        info!("[ Skip stopped at an unread line ]");
+    }
+
+    if playback.auto {
+        if !message.is_revealed() {
+            playback.auto_wait.reset();
+            return;
+        }
+        playback.auto_wait.tick(time.delta());
+        if playback.auto_wait.elapsed_secs() >= auto_delay(message.markup().grapheme_count()) {
+            playback.auto_wait.reset();
+            advance_message.write(ChatAdvanceMessage);
+        }
+    }
+}
+fn show_playback(
+    playback: Res<PlaybackMode>,
+    game_state: Res<VisualNovelState>,
+    mut button_query: Query<(&PlaybackButton, &mut BackgroundColor)>,
+    mut indicator_query: Query<&mut Text, With<PlaybackIndicator>>,
+) {
+    for (button, mut background) in button_query.iter_mut() {
+        let active = match button {
+            PlaybackButton::Auto => playback.auto,
+            PlaybackButton::Skip => playback.skip,
+            PlaybackButton::SkipUnread => game_state.persistent().skip_unread,
+        };
+        background.0 = if active { ACTIVE_COLOR } else { IDLE_COLOR };
+    }
+    for mut text in indicator_query.iter_mut() {
+        text.0 = if playback.skip {
+            String::from("Skipping >>")
+        } else if playback.auto {
+            String::from("Auto >")
+        } else {
+            String::new()
+        };
+    }
+}
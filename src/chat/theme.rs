@@ -0,0 +1,326 @@
+use crate::character::Configs;
+use crate::chat::controller::CharacterSayMessage;
+use crate::chat::markup::StyleFonts;
+use crate::display::DESIGN_RESOLUTION;
+use crate::{Object, VisualNovelState};
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use bevy::asset::AssetLoader;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::text::TextBounds;
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+pub const DEFAULT_THEME: &str = "themes/default.theme.json";
+
+/* Custom types */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ThemeAnchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    CenterLeft,
+    Center,
+    CenterRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+impl From<ThemeAnchor> for Anchor {
+    fn from(anchor: ThemeAnchor) -> Self {
+        match anchor {
+            ThemeAnchor::TopLeft => Anchor::TOP_LEFT,
+            ThemeAnchor::TopCenter => Anchor::TOP_CENTER,
+            ThemeAnchor::TopRight => Anchor::TOP_RIGHT,
+            ThemeAnchor::CenterLeft => Anchor::CENTER_LEFT,
+            ThemeAnchor::Center => Anchor::CENTER,
+            ThemeAnchor::CenterRight => Anchor::CENTER_RIGHT,
+            ThemeAnchor::BottomLeft => Anchor::BOTTOM_LEFT,
+            ThemeAnchor::BottomCenter => Anchor::BOTTOM_CENTER,
+            ThemeAnchor::BottomRight => Anchor::BOTTOM_RIGHT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ThemeJustify {
+    Left,
+    Center,
+    Right,
+}
+impl From<ThemeJustify> for Justify {
+    fn from(justify: ThemeJustify) -> Self {
+        match justify {
+            ThemeJustify::Left => Justify::Left,
+            ThemeJustify::Center => Justify::Center,
+            ThemeJustify::Right => Justify::Right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct ThemeBounds {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+}
+
+fn default_window_anchor() -> Vec2 {
+    Vec2::splat(0.5)
+}
+fn default_scale() -> f32 {
+    1.
+}
+
+// Where and how one GUI object is drawn. Positions are `window_anchor`, a
+//  fraction of the design resolution from its bottom left corner, moved by `offset` pixels
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ThemeElement {
+    #[serde(default = "default_window_anchor")]
+    pub window_anchor: Vec2,
+    #[serde(default)]
+    pub offset: Vec2,
+    #[serde(default)]
+    pub z: f32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+
+    // Sprite elements only, the GUI sprite shown when the speaker has none of their own
+    pub sprite: Option<String>,
+
+    // Text elements only
+    pub font: Option<String>,
+    pub font_size: Option<f32>,
+    // For `{b}` and `{i}` markup in dialogue
+    pub bold_font: Option<String>,
+    pub italic_font: Option<String>,
+    pub bold_italic_font: Option<String>,
+    // Hex, like "#ff0000" or "#ff000080"
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub color: Option<Color>,
+    pub anchor: Option<ThemeAnchor>,
+    pub justify: Option<ThemeJustify>,
+    pub bounds: Option<ThemeBounds>,
+}
+
+/* Assets */
+// Layout of the chat GUI, by object id
+#[derive(Asset, TypePath, Debug, Clone, PartialEq, Deserialize)]
+pub struct ChatTheme {
+    pub elements: HashMap<String, ThemeElement>,
+}
+
+#[derive(Debug, Error)]
+pub enum ChatThemeError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON parse error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+#[derive(Default)]
+pub struct ChatThemeLoader;
+impl AssetLoader for ChatThemeLoader {
+    type Asset = ChatTheme;
+    type Settings = ();
+    type Error = ChatThemeError;
+
+    fn load(
+            &self,
+            reader: &mut dyn bevy::asset::io::Reader,
+            _settings: &Self::Settings,
+            _load_context: &mut bevy::asset::LoadContext,
+        ) -> impl bevy::tasks::ConditionalSendFuture<Output = std::result::Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let parsed: ChatTheme = serde_json::from_slice(&bytes)?;
+            Ok(parsed)
+        })
+    }
+
+    // Longer than the character configs' "json", so theme files come here instead
+    fn extensions(&self) -> &[&str] {
+        &["theme.json"]
+    }
+}
+
+/* Resources */
+#[derive(Resource)]
+pub struct HandleToChatTheme(pub Handle<ChatTheme>);
+
+impl ThemeElement {
+    // Screen-space position of the element, with the origin at the screen's center
+    pub fn position(&self, screen_size: Vec2) -> Vec3 {
+        ((self.window_anchor - 0.5) * screen_size + self.offset).extend(self.z)
+    }
+}
+
+// Bad colors fail the load, so a broken edit keeps the last good theme
+pub fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    let Some(hex) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    Srgba::hex(&hex)
+        .map(|color| Some(Color::Srgba(color)))
+        .map_err(|_| serde::de::Error::custom(format!("Invalid color '{}'", hex)))
+}
+
+// Everything about a text object the theme can set
+type ThemedText = (
+    &'static mut TextFont,
+    &'static mut TextColor,
+    &'static mut Anchor,
+    &'static mut TextLayout,
+    &'static mut TextBounds,
+    Option<&'static mut StyleFonts>,
+);
+
+// Lays the GUI out again whenever the theme is edited or themed objects are spawned;
+//  the camera scales the design resolution to the window
+pub fn apply_theme(
+    mut theme_events: MessageReader<AssetEvent<ChatTheme>>,
+    added_query: Query<(), Added<Object>>,
+    themes: Res<Assets<ChatTheme>>,
+    theme_handle: Res<HandleToChatTheme>,
+    asset_server: Res<AssetServer>,
+
+    mut object_query: Query<(Entity, &Object, &mut Transform, Option<&ChildOf>)>,
+    mut text_query: Query<ThemedText>,
+) -> Result<(), BevyError> {
+    let theme_changed = theme_events.read()
+        .any(|event| event.is_loaded_with_dependencies(&theme_handle.0) || event.is_modified(&theme_handle.0));
+    if !theme_changed && added_query.is_empty() {
+        return Ok(());
+    }
+    let Some(theme) = themes.get(&theme_handle.0) else {
+        return Ok(());
+    };
+
+    // Children are placed relative to their parent, so work out every
+    //  themed object's window position first
+    let positions: HashMap<Entity, Vec3> = object_query.iter()
+        .filter_map(|(entity, obj, _, _)| theme.elements.get(&obj.id).map(|element| (entity, element.position(DESIGN_RESOLUTION))))
+        .collect();
+
+    for (entity, obj, mut transform, parent) in object_query.iter_mut() {
+        let Some(element) = theme.elements.get(&obj.id) else {
+            continue;
+        };
+        let parent_position = parent
+            .and_then(|parent| positions.get(&parent.parent()))
+            .copied()
+            .unwrap_or_default();
+        transform.translation = positions[&entity] - parent_position;
+        transform.scale = Vec3::splat(element.scale);
+
+        let Ok((mut font, mut color, mut anchor, mut layout, mut bounds, style_fonts)) = text_query.get_mut(entity) else {
+            continue;
+        };
+        if let Some(path) = &element.font {
+            font.font = asset_server.load(path.clone());
+        }
+        if let Some(font_size) = element.font_size {
+            font.font_size = font_size;
+        }
+        if let Some(theme_color) = element.color {
+            color.0 = theme_color;
+        }
+        if let Some(theme_anchor) = element.anchor {
+            *anchor = theme_anchor.into();
+        }
+        if let Some(justify) = element.justify {
+            layout.justify = justify.into();
+        }
+        if let Some(theme_bounds) = element.bounds {
+            *bounds = TextBounds { width: theme_bounds.width, height: theme_bounds.height };
+        }
+        if let Some(mut style_fonts) = style_fonts {
+            let load = |path: &Option<String>| path.as_ref().map(|path| asset_server.load(path.clone()));
+            *style_fonts = StyleFonts {
+                bold: load(&element.bold_font),
+                italic: load(&element.italic_font),
+                bold_italic: load(&element.bold_italic_font),
+            };
+        }
+    }
+    Ok(())
+}
+
+// The theme, and its edits
+#[derive(SystemParam)]
+pub struct ThemeAssets<'w, 's> {
+    theme_events: MessageReader<'w, 's, AssetEvent<ChatTheme>>,
+    themes: Res<'w, Assets<ChatTheme>>,
+    theme_handle: Res<'w, HandleToChatTheme>,
+}
+// The textbox and namebox parts a speaker's style changes
+#[derive(SystemParam)]
+pub struct TextboxParts<'w, 's> {
+    sprite_query: Query<'w, 's, (&'static Object, &'static mut Sprite)>,
+    text_query: Query<'w, 's, (&'static Object, &'static mut TextFont, &'static mut TextColor)>,
+}
+
+// Styles the textbox for whoever is speaking. Characters without a style of their
+//  own, like MC, get the theme's; runs after `apply_theme` so edits keep the style
+pub fn style_speaker(
+    mut say_messages: MessageReader<CharacterSayMessage>,
+    mut speaker: Local<Option<String>>,
+    theme_assets: ThemeAssets,
+    configs: Option<Res<Configs>>,
+    game_state: Res<VisualNovelState>,
+    asset_server: Res<AssetServer>,
+
+    textbox_parts: TextboxParts,
+) -> Result<(), BevyError> {
+    let ThemeAssets { mut theme_events, themes, theme_handle } = theme_assets;
+    let TextboxParts { mut sprite_query, mut text_query } = textbox_parts;
+    let mut restyle = theme_events.read().any(|event| event.is_modified(&theme_handle.0));
+    for msg in say_messages.read() {
+        *speaker = Some(msg.name.clone());
+        restyle = true;
+    }
+    if !restyle {
+        return Ok(());
+    }
+    let (Some(name), Some(theme)) = (speaker.as_ref(), themes.get(&theme_handle.0)) else {
+        return Ok(());
+    };
+    let style = configs.as_ref().and_then(|configs| configs.0.get(name));
+    let theme_element = |id: &str| theme.elements.get(id);
+
+    for (obj, mut sprite) in sprite_query.iter_mut() {
+        let own_sprite = match obj.id.as_str() {
+            "_textbox_background" => style.and_then(|config| config.textbox.as_ref()),
+            "_namebox_background" => style.and_then(|config| config.namebox.as_ref()),
+            _ => continue,
+        };
+        // Without either, whatever a script last set stays
+        let Some(sprite_id) = own_sprite.or(theme_element(&obj.id).and_then(|element| element.sprite.as_ref())) else {
+            continue;
+        };
+        let gui_sprite = game_state.gui_sprites.get(sprite_id)
+            .with_context(|| format!("GUI asset '{}' for '{}' does not exist", sprite_id, name))?;
+        sprite.image = gui_sprite.clone();
+    }
+
+    for (obj, mut font, mut color) in text_query.iter_mut() {
+        let (own_color, own_font) = match obj.id.as_str() {
+            "_name_text" => (style.and_then(|config| config.name_color), None),
+            "_message_text" => (style.and_then(|config| config.text_color), style.and_then(|config| config.font.as_ref())),
+            _ => continue,
+        };
+        let element = theme_element(&obj.id);
+        color.0 = own_color
+            .or(element.and_then(|element| element.color))
+            .unwrap_or(Color::WHITE);
+        font.font = match own_font.or(element.and_then(|element| element.font.as_ref())) {
+            Some(path) => asset_server.load(path.clone()),
+            None => TextFont::default().font,
+        };
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use crate::settings::read_settings;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/* Resources */
+// How lines type out when the markup doesn't say otherwise; `settings/text.json`
+//  overrides any of it
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Typewriter {
+    // Seconds between characters; a speaker's `character_delay` replaces it
+    pub character_delay: f32,
+    // Extra seconds after these, when they end a sentence rather than sit inside one
+    pub punctuation_pauses: HashMap<String, f32>,
+}
+impl Default for Typewriter {
+    fn default() -> Self {
+        Self {
+            character_delay: 0.02,
+            punctuation_pauses: HashMap::from([
+                (String::from("."), 0.25),
+                (String::from("!"), 0.25),
+                (String::from("?"), 0.25),
+                (String::from("\u{2026}"), 0.4),
+                (String::from("\u{3002}"), 0.25),
+                (String::from("\u{ff01}"), 0.25),
+                (String::from("\u{ff1f}"), 0.25),
+            ]),
+        }
+    }
+}
+impl Typewriter {
+    pub fn with_character_delay(&self, character_delay: Option<f32>) -> Self {
+        Self {
+            character_delay: character_delay.unwrap_or(self.character_delay),
+            ..self.clone()
+        }
+    }
+
+    // Pause after `grapheme` before `next`, so "..." or "?!" pause once, and
+    //  "3.14" not at all. Only the next grapheme is known, so "e.g." still
+    //  pauses at its last dot when a space follows
+    pub fn pause_between(&self, grapheme: &str, next: &str) -> f32 {
+        let Some(pause) = self.punctuation_pauses.get(grapheme) else {
+            return 0.;
+        };
+        let continues_word = next.chars().all(|c| c.is_ascii_alphanumeric());
+        if self.punctuation_pauses.contains_key(next) || continues_word {
+            return 0.;
+        }
+        *pause
+    }
+}
+
+pub fn typewriter_path() -> PathBuf {
+    PathBuf::from(".").join("settings").join("text.json")
+}
+pub fn load_typewriter(mut typewriter: ResMut<Typewriter>) -> Result<(), BevyError> {
+    *typewriter = read_settings(&typewriter_path())?;
+    Ok(())
+}
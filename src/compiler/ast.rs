@@ -1,10 +1,14 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use pest::{iterators::Pair, pratt_parser::PrattParser};
+use pest::{iterators::{Pair, Pairs}, pratt_parser::PrattParser, Parser as _};
 use pest_derive::Parser;
-use anyhow::{bail, ensure, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
 
-use crate::character::CharacterOperation;
+use crate::background::{BackgroundTransition, TransitionEffect, WipeDirection};
+use crate::character::{CharacterOperation, StageSlot};
+use crate::compiler::diagnostics::{SourceFile, Span};
 
 #[derive(Parser)]
 #[grammar = "../sabi.pest"]
@@ -15,36 +19,111 @@ lazy_static::lazy_static! {
         use pest::pratt_parser::{Assoc::*, Op};
         // Precedence is defined from lowest to highest priority
         PrattParser::new()
+            .op(Op::infix(Rule::or, Left))
+            .op(Op::infix(Rule::and, Left))
+            .op(Op::prefix(Rule::not))
+            .op(Op::infix(Rule::eq, Left) | Op::infix(Rule::neq, Left))
+            .op(Op::infix(Rule::lt, Left) | Op::infix(Rule::le, Left) | Op::infix(Rule::gt, Left) | Op::infix(Rule::ge, Left))
             .op(Op::infix(Rule::add, Left))
     };
 }
 
 // Trait for evaluating expressions by flattening them
 pub trait Evaluate {
-    fn evaluate_into_string(&self) -> Result<String>;
-    fn evaluate(&self) -> Result<Expr>;
+    fn evaluate_into_string(&self, variables: &impl Scope) -> Result<String>;
+    fn evaluate(&self, variables: &impl Scope) -> Result<Expr>;
 }
 
-#[derive(Debug, Clone)]
+// Where identifiers in expressions are looked up
+pub trait Scope {
+    fn lookup(&self, name: &str) -> Option<Expr>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expr {
     Number(f64),
     String(String),
-    Add { lhs: Box<Expr>, rhs: Box<Expr> }
+    Boolean(bool),
+    Identifier(String),
+    Add { lhs: Box<Expr>, rhs: Box<Expr> },
+    Compare { op: Comparison, lhs: Box<Expr>, rhs: Box<Expr> },
+    And { lhs: Box<Expr>, rhs: Box<Expr> },
+    Or { lhs: Box<Expr>, rhs: Box<Expr> },
+    Not { expr: Box<Expr> },
+    // A string with `[expr]` parts, joined once they're evaluated
+    Interpolation { parts: Vec<Expr> },
+}
+
+impl Expr {
+    // Human-readable name of a value's type, used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Expr::Number(_) => "number",
+            Expr::String(_) => "string",
+            Expr::Boolean(_) => "boolean",
+            _ => "expression",
+        }
+    }
+
+    // Evaluates the expression, requiring the result to be a boolean
+    pub fn evaluate_into_bool(&self, variables: &impl Scope) -> Result<bool> {
+        match self.evaluate(variables)? {
+            Expr::Boolean(b) => Ok(b),
+            other => bail!("Expected a boolean, found a {}", other.type_name()),
+        }
+    }
+}
+
+fn compare(op: Comparison, left: &Expr, right: &Expr) -> Result<bool> {
+    let ordering = match (left, right) {
+        (Expr::Number(l), Expr::Number(r)) => l.partial_cmp(r)
+            .context("Cannot compare NaN")?,
+        (Expr::String(l), Expr::String(r)) => l.cmp(r),
+        (Expr::Boolean(l), Expr::Boolean(r)) => {
+            ensure!(matches!(op, Comparison::Equal | Comparison::NotEqual),
+                "Booleans can only be compared with '==' or '!='");
+            l.cmp(r)
+        },
+        _ => bail!("Cannot compare a {} with a {}", left.type_name(), right.type_name()),
+    };
+
+    Ok(match op {
+        Comparison::Equal => ordering.is_eq(),
+        Comparison::NotEqual => ordering.is_ne(),
+        Comparison::Less => ordering.is_lt(),
+        Comparison::LessOrEqual => ordering.is_le(),
+        Comparison::Greater => ordering.is_gt(),
+        Comparison::GreaterOrEqual => ordering.is_ge(),
+    })
 }
 
 impl Evaluate for Expr {
-    fn evaluate_into_string(&self) -> Result<String> {
-        let evaluated = self.evaluate()
+    fn evaluate_into_string(&self, variables: &impl Scope) -> Result<String> {
+        let evaluated = self.evaluate(variables)
             .context("Failed to evaluate expression")?;
-        expr_to_string(&evaluated)
+        expr_to_string(&evaluated, variables)
             .context("Failed to convert evaluated expression to string")
     }
-    fn evaluate(&self) -> Result<Expr> {
+    fn evaluate(&self, variables: &impl Scope) -> Result<Expr> {
         match self {
-            Expr::String(_) | Expr::Number(_) => Ok(self.clone()),
+            Expr::String(_) | Expr::Number(_) | Expr::Boolean(_) => Ok(self.clone()),
+            Expr::Identifier(name) => {
+                variables.lookup(name)
+                    .with_context(|| format!("Variable '{}' is not defined", name))
+            },
             Expr::Add { lhs, rhs } => {
-                let left = lhs.evaluate().context("Failed to evaluate left side of addition")?;
-                let right = rhs.evaluate().context("Failed to evaluate right side of addition")?;
+                let left = lhs.evaluate(variables).context("Failed to evaluate left side of addition")?;
+                let right = rhs.evaluate(variables).context("Failed to evaluate right side of addition")?;
                 
                 match (&left, &right) {
                     (Expr::Number(l), Expr::Number(r)) => {
@@ -61,70 +140,220 @@ impl Evaluate for Expr {
                     },
                     _ => {
                         // For complex expressions, convert to strings and concatenate
-                        let left_str = expr_to_string(&left)?;
-                        let right_str = expr_to_string(&right)?;
+                        let left_str = expr_to_string(&left, variables)?;
+                        let right_str = expr_to_string(&right, variables)?;
                         Ok(Expr::String(format!("{}{}", left_str, right_str)))
                     }
                 }
-            }
+            },
+            Expr::Compare { op, lhs, rhs } => {
+                let left = lhs.evaluate(variables).context("Failed to evaluate left side of comparison")?;
+                let right = rhs.evaluate(variables).context("Failed to evaluate right side of comparison")?;
+
+                compare(*op, &left, &right)
+                    .map(Expr::Boolean)
+                    .with_context(|| format!("Failed to compare {:?} with {:?}", left, right))
+            },
+            Expr::And { lhs, rhs } => {
+                // Short-circuit so the right side may rely on the left
+                if !lhs.evaluate_into_bool(variables).context("Failed to evaluate left side of 'and'")? {
+                    return Ok(Expr::Boolean(false));
+                }
+                rhs.evaluate_into_bool(variables)
+                    .map(Expr::Boolean)
+                    .context("Failed to evaluate right side of 'and'")
+            },
+            Expr::Or { lhs, rhs } => {
+                if lhs.evaluate_into_bool(variables).context("Failed to evaluate left side of 'or'")? {
+                    return Ok(Expr::Boolean(true));
+                }
+                rhs.evaluate_into_bool(variables)
+                    .map(Expr::Boolean)
+                    .context("Failed to evaluate right side of 'or'")
+            },
+            Expr::Not { expr } => {
+                expr.evaluate_into_bool(variables)
+                    .map(|b| Expr::Boolean(!b))
+                    .context("Failed to evaluate operand of 'not'")
+            },
+            Expr::Interpolation { parts } => {
+                parts.iter()
+                    .map(|part| part.evaluate_into_string(variables))
+                    .collect::<Result<String>>()
+                    .map(Expr::String)
+                    .context("Failed to evaluate interpolated string")
+            },
         }
     }
 }
 
 // Helper function to convert Expr to String
-pub fn expr_to_string(expr: &Expr) -> Result<String> {
+pub fn expr_to_string(expr: &Expr, variables: &impl Scope) -> Result<String> {
     match expr {
         Expr::String(s) => Ok(s.clone()),
         Expr::Number(n) => Ok(n.to_string()),
-        Expr::Add { .. } => {
-            let evaluated = expr.evaluate()?;
-            expr_to_string(&evaluated)
+        Expr::Boolean(b) => Ok(b.to_string()),
+        _ => {
+            let evaluated = expr.evaluate(variables)?;
+            expr_to_string(&evaluated, variables)
         }
     }
 }
 
+// Script variables, holding only fully evaluated values
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Variables(HashMap<String, Expr>);
+
+impl Variables {
+    pub fn get(&self, name: &str) -> Option<&Expr> {
+        self.0.get(name)
+    }
+
+    // Assigns a value, refusing to change the type of an existing variable
+    pub fn set(&mut self, name: &str, value: Expr) -> Result<()> {
+        ensure!(matches!(value, Expr::Number(_) | Expr::String(_) | Expr::Boolean(_)),
+            "Cannot assign an unevaluated {} to variable '{}'", value.type_name(), name);
+
+        if let Some(existing) = self.0.get(name) {
+            ensure!(std::mem::discriminant(existing) == std::mem::discriminant(&value),
+                "Variable '{}' is a {}, cannot assign a {} to it", name, existing.type_name(), value.type_name());
+        }
+
+        self.0.insert(name.to_owned(), value);
+        Ok(())
+    }
+}
+impl Scope for Variables {
+    fn lookup(&self, name: &str) -> Option<Expr> {
+        self.get(name).cloned()
+    }
+}
+
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Act {
     pub scenes: HashMap<String, Box<Scene>>,
     pub entrypoint: String,
+    // The script the act was compiled from
+    #[serde(skip)]
+    pub source: Arc<SourceFile>,
 }
 pub type Acts = HashMap<String, Box<Act>>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum CodeStatement {
-    Log { exprs: Vec<Expr> }
+    Log { exprs: Vec<Expr> },
+    Set { variable: String, expr: Expr },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum StageCommand {
-    BackgroundChange { background_expr: Box<Expr> },
+    BackgroundChange { background_expr: Box<Expr>, transition: Option<BackgroundTransition> },
     GUIChange { id_expr: Box<Expr>, sprite_expr: Box<Expr> },
     SceneChange { scene_expr: Box<Expr> },
     ActChange { act_expr: Box<Expr> },
     CharacterChange { character: String, operation: CharacterOperation },
+    // A missing expression stops the music
+    MusicChange { music_expr: Option<Box<Expr>>, fade: f32 },
+    SoundChange { sound_expr: Box<Expr> },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Dialogue {
     pub character: String,
-    pub dialogue: Expr
+    pub dialogue: Expr,
+    pub voice: Option<Expr>,
 }
 
-#[derive(Debug, Clone)]
-pub enum Statement {
+#[derive(Debug, Clone, Serialize)]
+pub struct ChoiceOption {
+    pub label: Expr,
+    pub block: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum StatementKind {
     Code(CodeStatement),
     Stage(StageCommand),
-    Dialogue(Dialogue)
+    Dialogue(Dialogue),
+    // `else if` chains are nested as an `If` inside `else_block`
+    If { cond: Expr, then_block: Vec<Statement>, else_block: Vec<Statement> },
+    Choice { prompt: Option<Expr>, options: Vec<ChoiceOption> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    // The nested block selected by `branch`, for statements that have them
+    pub fn block(&self, branch: usize) -> Option<&Vec<Statement>> {
+        match &self.kind {
+            StatementKind::If { then_block, else_block, .. } => [then_block, else_block].get(branch).copied(),
+            StatementKind::Choice { options, .. } => options.get(branch).map(|option| &option.block),
+            _ => None,
+        }
+    }
 }
 
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Scene {
     pub statements: Vec<Statement>
 }
 
+// Splits `[expr]` parts out of a string literal; `[[` is a literal bracket
+pub fn build_string(s: &str) -> Result<Expr> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('[') {
+        literal.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        if let Some(escaped) = rest.strip_prefix('[') {
+            literal.push('[');
+            rest = escaped;
+            continue;
+        }
+        let end = rest.find(']')
+            .with_context(|| format!("Unclosed '[' in string \"{}\"", s))?;
+        let source = &rest[..end];
+        // Speakers used to be written "[_PLAYERNAME_]", before `player.name`
+        ensure!(source != "_PLAYERNAME_", "'[_PLAYERNAME_]' is no longer supported, write '[player.name]' for the player's name");
+        let mut pairs = SabiParser::parse(Rule::interpolation, source)
+            .with_context(|| format!("Invalid expression '[{}]' in string \"{}\"", source, s))?;
+        let expr = pairs.next()
+            .and_then(|interpolation| interpolation.into_inner().next())
+            .context("Interpolation is missing its expression")?;
+        if !literal.is_empty() {
+            parts.push(Expr::String(std::mem::take(&mut literal)));
+        }
+        parts.push(build_expression(expr)?);
+        rest = &rest[end + 1..];
+    }
+    literal.push_str(rest);
+
+    if parts.is_empty() {
+        return Ok(Expr::String(literal));
+    }
+    if !literal.is_empty() {
+        parts.push(Expr::String(literal));
+    }
+    Ok(Expr::Interpolation { parts })
+}
+
+// Strings are taken as written, so ids like backgrounds, music and GUI sprites
+//  can hold brackets; only text shown to the player interpolates, see `build_text`
 pub fn build_expression(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+    build_expression_with(pair, false)
+}
+// Dialogue, choice prompts and choice labels, where `[expr]` is filled in
+pub fn build_text(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
+    build_expression_with(pair, true)
+}
+fn build_expression_with(pair: pest::iterators::Pair<Rule>, interpolate: bool) -> Result<Expr> {
     PRATT_PARSER
         .map_primary(|primary| match primary.as_rule() {
             Rule::number => {
@@ -136,38 +365,157 @@ pub fn build_expression(pair: pest::iterators::Pair<Rule>) -> Result<Expr> {
                 let s = primary.as_str();
                 // Remove the surrounding quotes
                 let s = &s[1..s.len()-1];
-                Ok(Expr::String(s.to_string()))
+                if interpolate {
+                    build_string(s)
+                } else {
+                    Ok(Expr::String(s.to_owned()))
+                }
             },
-            Rule::expr => build_expression(primary),
+            Rule::boolean => Ok(Expr::Boolean(primary.as_str() == "true")),
+            Rule::identifier => Ok(Expr::Identifier(primary.as_str().to_owned())),
+            Rule::expr => build_expression_with(primary, interpolate),
             other => bail!("Unexpected primary expr: {other:?}"),
         })
-        .map_infix(|left, op, right| {
+        .map_prefix(|op, operand| {
             match op.as_rule() {
-                Rule::add => Ok(Expr::Add {
-                    lhs: Box::new(left.context("Failed to evaluate left operand")?),
-                    rhs: Box::new(right.context("Failed to evaluate right operand")?),
+                Rule::not => Ok(Expr::Not {
+                    expr: Box::new(operand.context("Failed to evaluate operand")?),
                 }),
-                other => bail!("Unexpected infix operator: {other:?}"),
+                other => bail!("Unexpected prefix operator: {other:?}"),
             }
         })
+        .map_infix(|left, op, right| {
+            let lhs = Box::new(left.context("Failed to evaluate left operand")?);
+            let rhs = Box::new(right.context("Failed to evaluate right operand")?);
+
+            let comparison = match op.as_rule() {
+                Rule::add => return Ok(Expr::Add { lhs, rhs }),
+                Rule::and => return Ok(Expr::And { lhs, rhs }),
+                Rule::or => return Ok(Expr::Or { lhs, rhs }),
+                Rule::eq => Comparison::Equal,
+                Rule::neq => Comparison::NotEqual,
+                Rule::lt => Comparison::Less,
+                Rule::le => Comparison::LessOrEqual,
+                Rule::gt => Comparison::Greater,
+                Rule::ge => Comparison::GreaterOrEqual,
+                other => bail!("Unexpected infix operator: {other:?}"),
+            };
+            Ok(Expr::Compare { op: comparison, lhs, rhs })
+        })
         .parse(pair.into_inner())
         .context("Failed to parse expression")
 }
 
-pub fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
+// Seconds music takes to fade out when no duration is given
+const DEFAULT_MUSIC_FADE: f32 = 1.0;
+
+// How long `(Nayu moves to left)` takes without `over`
+const DEFAULT_MOVE_DURATION: f32 = 0.5;
+// And `(Nayu fade in)`
+const DEFAULT_CHARACTER_FADE: f32 = 1.0;
+
+// And `(Background changes to "x" with dissolve)`
+const DEFAULT_TRANSITION_DURATION: f32 = 1.0;
+
+// Seconds a character fades over, or 0 to appear or leave at once
+fn build_character_fade(duration_pair: Option<Pair<Rule>>, fading: bool) -> Result<f32> {
+    match duration_pair {
+        Some(duration_pair) => {
+            ensure!(fading, "Only 'fade in' and 'fade out' take a duration");
+            build_fade_duration(duration_pair).context("Failed to build character fade duration")
+        },
+        None if fading => Ok(DEFAULT_CHARACTER_FADE),
+        None => Ok(0.),
+    }
+}
+
+pub fn build_stage_slot(pair: Pair<Rule>) -> Result<StageSlot> {
+    ensure!(pair.as_rule() == Rule::stage_position,
+        "Expected stage position, found {:?}", pair.as_rule());
+
+    let position_pair = pair.into_inner().next()
+        .context("Stage position missing slot")?;
+    match position_pair.as_rule() {
+        Rule::stage_slot => match position_pair.as_str() {
+            "left" => Ok(StageSlot::Left),
+            "center" => Ok(StageSlot::Center),
+            "right" => Ok(StageSlot::Right),
+            other => bail!("Unknown stage slot '{}'", other),
+        },
+        Rule::number => {
+            let x = position_pair.as_str().parse::<f32>()
+                .context("Failed to parse stage position")?;
+            ensure!((0. ..=1.).contains(&x), "Stage positions go from 0 (left edge) to 1 (right edge), found {}", x);
+            Ok(StageSlot::At(x))
+        },
+        other => bail!("Unexpected rule in stage position: {:?}", other),
+    }
+}
+
+pub fn build_background_transition(pair: Pair<Rule>) -> Result<BackgroundTransition> {
+    ensure!(pair.as_rule() == Rule::background_transition,
+        "Expected background transition, found {:?}", pair.as_rule());
+
+    let mut inner = pair.into_inner();
+    let effect_pair = inner.next()
+        .context("Background transition missing effect")?;
+    let effect = match effect_pair.as_rule() {
+        Rule::dissolve => TransitionEffect::Dissolve(effect_pair.into_inner().next().map(|mask_pair| mask_pair.as_str().to_owned())),
+        Rule::wipe => {
+            let direction_pair = effect_pair.into_inner().next()
+                .context("Wipe missing direction")?;
+            TransitionEffect::Wipe(match direction_pair.as_str() {
+                "left" => WipeDirection::Left,
+                "right" => WipeDirection::Right,
+                "up" => WipeDirection::Up,
+                "down" => WipeDirection::Down,
+                other => bail!("Unknown wipe direction '{}'", other),
+            })
+        },
+        Rule::fade_through_black => TransitionEffect::FadeThroughBlack,
+        other => bail!("Unexpected rule in background transition: {:?}", other),
+    };
+    let duration = match inner.next() {
+        Some(number_pair) => number_pair.as_str().parse::<f32>()
+            .context("Failed to parse transition duration")?,
+        None => DEFAULT_TRANSITION_DURATION,
+    };
+    ensure!(duration >= 0., "Transition duration can't be negative, found {}", duration);
+    Ok(BackgroundTransition { effect, duration })
+}
+
+pub fn build_fade_duration(pair: Pair<Rule>) -> Result<f32> {
+    ensure!(pair.as_rule() == Rule::fade_duration,
+        "Expected fade duration, found {:?}", pair.as_rule());
+
+    let number_pair = pair.into_inner().next()
+        .context("Fade duration missing number")?;
+    let seconds = number_pair.as_str().parse::<f32>()
+        .context("Failed to parse fade duration")?;
+    ensure!(seconds >= 0., "Fade duration can't be negative, found {}", seconds);
+    Ok(seconds)
+}
+
+pub fn build_stage_command(pair: Pair<Rule>, file: &Arc<SourceFile>) -> Result<Statement> {
     ensure!(pair.as_rule() == Rule::stage_command, 
         "Expected stage rule, found {:?}", pair.as_rule());
     
+    let span = Span::of(&pair, file);
     let command_pair = pair.into_inner().next()
         .context("Stage command missing inner command")?;
     
     let result = match command_pair.as_rule() {
         Rule::background_change => {
-            let expr_pair = command_pair.into_inner().next()
+            let mut inner = command_pair.into_inner();
+            let expr_pair = inner.next()
                 .context("Background change missing expression")?;
             let expr = build_expression(expr_pair)
                 .context("Failed to build expression for background change")?;
-            StageCommand::BackgroundChange { background_expr: Box::new(expr) }
+            let transition = inner.next()
+                .map(build_background_transition)
+                .transpose()
+                .context("Failed to build background transition")?;
+            StageCommand::BackgroundChange { background_expr: Box::new(expr), transition }
         },
         Rule::gui_change => {
             let mut inner = command_pair.into_inner();
@@ -205,34 +553,102 @@ pub fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
                 .context("Failed to build expression for act change")?;
             StageCommand::ActChange { act_expr: Box::new(expr) }
         },
+        Rule::music_change => {
+            let mut music_expr = None;
+            let mut fade = 0.;
+            for inner_pair in command_pair.into_inner() {
+                match inner_pair.as_rule() {
+                    Rule::expr => {
+                        let expr = build_expression(inner_pair)
+                            .context("Failed to build expression for music change")?;
+                        music_expr = Some(Box::new(expr));
+                    },
+                    Rule::music_action => {
+                        if inner_pair.as_str() == "fades out" {
+                            fade = DEFAULT_MUSIC_FADE;
+                        }
+                    },
+                    Rule::fade_duration => {
+                        fade = build_fade_duration(inner_pair)
+                            .context("Failed to build music fade duration")?;
+                    },
+                    other => bail!("Unexpected rule in music change: {:?}", other)
+                }
+            }
+            StageCommand::MusicChange { music_expr, fade }
+        },
+        Rule::sound_change => {
+            let expr_pair = command_pair.into_inner().next()
+                .context("Sound change missing expression")?;
+            let expr = build_expression(expr_pair)
+                .context("Failed to build expression for sound change")?;
+            StageCommand::SoundChange { sound_expr: Box::new(expr) }
+        },
         Rule::character_change => {
             let mut inner_rules = command_pair.into_inner().peekable();
             let character = inner_rules.next()
                 .context("Character change missing character identifier")?
                 .as_str()
                 .to_owned();
-            let action = inner_rules.next()
-                .context("Character change missing character action")?
-                .as_str()
-                .to_owned();
+            let action_pair = inner_rules.next()
+                .context("Character change missing character action")?;
+            // "moves" and "to" may have any whitespace between them
+            let action = match action_pair.as_rule() {
+                Rule::move_action => String::from("moves to"),
+                _ => action_pair.as_str().to_owned(),
+            };
+
+            let slot = match inner_rules.peek() {
+                Some(n) if n.as_rule() == Rule::stage_position => {
+                    let position_pair = inner_rules.next()
+                        .context("Expected stage position pair")?;
+                    Some(build_stage_slot(position_pair)?)
+                },
+                _ => None
+            };
             match action.as_str() {
                 "appears" | "fade in" => {
                     let fading = action.as_str() == "fade in";
-                    let operation = match inner_rules.peek() {
+                    let emotion = match inner_rules.peek() {
                         Some(n) if n.as_rule() == Rule::emotion_name => {
                             let emotion_pair = inner_rules.next()
                                 .context("Expected emotion pair")?;
                             
                             ensure!(emotion_pair.as_rule() == Rule::emotion_name,
                                 "Expected emotion name, found {:?}", emotion_pair.as_rule());
-                            CharacterOperation::Spawn(Some(emotion_pair.as_str().to_owned()), fading)
+                            Some(emotion_pair.as_str().to_owned())
                         },
-                        _ => CharacterOperation::Spawn(None, fading)
+                        _ => None
                     };
-                    StageCommand::CharacterChange { character, operation }
+                    let fade = build_character_fade(inner_rules.next(), fading)?;
+                    StageCommand::CharacterChange { character, operation: CharacterOperation::Spawn(emotion, slot, fade) }
                 },
                 "disappears" | "fade out" => {
-                    StageCommand::CharacterChange { character, operation: CharacterOperation::Despawn(action.as_str() == "fade out") }
+                    ensure!(slot.is_none(), "Characters leaving the stage can't be given a position");
+                    let fade = build_character_fade(inner_rules.next(), action.as_str() == "fade out")?;
+                    StageCommand::CharacterChange { character, operation: CharacterOperation::Despawn(fade) }
+                },
+                "moves to" => {
+                    let slot = slot.context("Character move missing stage position")?;
+                    let duration = match inner_rules.next() {
+                        Some(duration_pair) => build_fade_duration(duration_pair)
+                            .context("Failed to build move duration")?,
+                        None => DEFAULT_MOVE_DURATION,
+                    };
+                    StageCommand::CharacterChange { character, operation: CharacterOperation::Move(slot, duration) }
+                },
+                "wears" => {
+                    let outfit = inner_rules.next()
+                        .context("Outfit change missing outfit name")?
+                        .as_str()
+                        .to_owned();
+                    // Without `over` the new outfit shows at once
+                    let crossfade = match inner_rules.next() {
+                        Some(duration_pair) => build_fade_duration(duration_pair)
+                            .context("Failed to build outfit crossfade duration")?,
+                        None => 0.,
+                    };
+                    StageCommand::CharacterChange { character, operation: CharacterOperation::OutfitChange(outfit, crossfade) }
                 },
                 other => bail!("Unexpected action in Character Change command: {:?}", other)
             }
@@ -240,13 +656,14 @@ pub fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
         other => bail!("Unexpected rule in stage command: {:?}", other)
     };
     
-    Ok(Statement::Stage(result))
+    Ok(Statement { kind: StatementKind::Stage(result), span })
 }
 
-pub fn build_code_statement(code_pair: Pair<Rule>) -> Result<Statement> {
+pub fn build_code_statement(code_pair: Pair<Rule>, file: &Arc<SourceFile>) -> Result<Statement> {
     ensure!(code_pair.as_rule() == Rule::code, 
         "Expected code rule, found {:?}", code_pair.as_rule());
     
+    let span = Span::of(&code_pair, file);
     let statement_pair = code_pair.into_inner().next()
         .context("Code block missing statement")?;
     
@@ -260,13 +677,32 @@ pub fn build_code_statement(code_pair: Pair<Rule>) -> Result<Statement> {
             }
             CodeStatement::Log { exprs }
         },
+        Rule::set => {
+            let mut inner = statement_pair.into_inner();
+            let variable = inner.next()
+                .context("Set statement missing variable name")?
+                .as_str()
+                .to_owned();
+            let expr_pair = inner.next()
+                .context("Set statement missing expression")?;
+            let expr = build_expression(expr_pair)
+                .context("Failed to build expression for set statement")?;
+            CodeStatement::Set { variable, expr }
+        },
         other => bail!("Unexpected rule in code statement: {:?}", other)
     };
     
-    Ok(Statement::Code(result))
+    Ok(Statement { kind: StatementKind::Code(result), span })
 }
 
-pub fn build_dialogue(pair: Pair<Rule>) -> Result<Vec<Statement>> {
+fn build_dialogue_voice(pair: Pair<Rule>) -> Result<Expr> {
+    let expr_pair = pair.into_inner().next()
+        .context("Voice clip missing expression")?;
+    build_expression(expr_pair)
+        .context("Failed to build expression for voice clip")
+}
+
+pub fn build_dialogue(pair: Pair<Rule>, file: &Arc<SourceFile>) -> Result<Vec<Statement>> {
     ensure!(pair.as_rule() == Rule::dialogue, 
         "Expected dialogue, found {:?}", pair.as_rule());
     
@@ -287,10 +723,20 @@ pub fn build_dialogue(pair: Pair<Rule>) -> Result<Vec<Statement>> {
             ensure!(emotion_name_pair.as_rule() == Rule::emotion_name, 
                 "Expected emotion name, found {:?}", emotion_name_pair.as_rule());
             
-            Some(Statement::Stage(StageCommand::CharacterChange { 
-                character: character.clone(), 
-                operation: CharacterOperation::EmotionChange(emotion_name_pair.as_str().to_owned())
-            }))
+            Some(Statement {
+                kind: StatementKind::Stage(StageCommand::CharacterChange { 
+                    character: character.clone(), 
+                    operation: CharacterOperation::EmotionChange(emotion_name_pair.as_str().to_owned())
+                }),
+                span: Span::of(&emotion_name_pair, file),
+            })
+        },
+        _ => None
+    };
+
+    let initial_voice = match inner_rules.peek() {
+        Some(n) if n.as_rule() == Rule::dialogue_voice => {
+            Some(build_dialogue_voice(inner_rules.next().context("Expected voice pair")?)?)
         },
         _ => None
     };
@@ -301,13 +747,18 @@ pub fn build_dialogue(pair: Pair<Rule>) -> Result<Vec<Statement>> {
         ensure!(dialogue_text_pair.as_rule() == Rule::expr, 
             "Expected dialogue text, found {:?}", dialogue_text_pair.as_rule());
         
-        let dialogue = build_expression(dialogue_text_pair)
+        let span = Span::of(&dialogue_text_pair, file);
+        let dialogue = build_text(dialogue_text_pair)
             .context("Failed to build expression for dialogue text")?;
         
-        Statement::Dialogue(Dialogue {
-            character: character.clone(),
-            dialogue
-        })
+        Statement {
+            kind: StatementKind::Dialogue(Dialogue {
+                character: character.clone(),
+                dialogue,
+                voice: initial_voice
+            }),
+            span,
+        }
     };
 
     let statements = {
@@ -316,19 +767,28 @@ pub fn build_dialogue(pair: Pair<Rule>) -> Result<Vec<Statement>> {
             statements.insert(0, emotion_stmt);
         }
 
+        let mut voice = None;
         while let Some(dialogue_text_pair) = inner_rules.next() {
             match dialogue_text_pair.as_rule() {
+                Rule::dialogue_voice => {
+                    voice = Some(build_dialogue_voice(dialogue_text_pair)?);
+                },
                 Rule::expr => {
-                    let dialogue = build_expression(dialogue_text_pair)
+                    let span = Span::of(&dialogue_text_pair, file);
+                    let dialogue = build_text(dialogue_text_pair)
                         .context("Failed to build expression for dialogue text")?;
 
-                    statements.push(Statement::Dialogue(Dialogue {
-                        character: character.clone(),
-                        dialogue
-                    }));
+                    statements.push(Statement {
+                        kind: StatementKind::Dialogue(Dialogue {
+                            character: character.clone(),
+                            dialogue,
+                            voice: voice.take()
+                        }),
+                        span,
+                    });
                 },
                 Rule::stage_command => {
-                    let stage_stmt = build_stage_command(dialogue_text_pair)
+                    let stage_stmt = build_stage_command(dialogue_text_pair, file)
                         .context("Failed to build stage command inside dialogue")?;
                     statements.push(stage_stmt);
                 },
@@ -342,16 +802,134 @@ pub fn build_dialogue(pair: Pair<Rule>) -> Result<Vec<Statement>> {
     Ok(statements)
 }
 
-pub fn build_scenes(pair: Pair<Rule>) -> Result<Act> {
+pub fn build_conditional(pair: Pair<Rule>, file: &Arc<SourceFile>) -> Result<Statement> {
+    ensure!(pair.as_rule() == Rule::conditional,
+        "Expected conditional, found {:?}", pair.as_rule());
+
+    // Collect each `if` / `else if` branch along with the final `else`
+    let mut branches = Vec::new();
+    let mut else_block = Vec::new();
+    for branch_pair in pair.into_inner() {
+        match branch_pair.as_rule() {
+            Rule::if_branch | Rule::else_if_branch => {
+                let span = Span::of(&branch_pair, file);
+                let mut inner = branch_pair.into_inner();
+                let cond = build_expression(inner.next().context("Conditional branch missing condition")?)
+                    .context("Failed to build condition expression")?;
+                let block = build_statements(inner.next().context("Conditional branch missing block")?.into_inner(), file)
+                    .context("Failed to build conditional block")?;
+                branches.push((cond, block, span));
+            },
+            Rule::else_branch => {
+                let block_pair = branch_pair.into_inner().next()
+                    .context("Else branch missing block")?;
+                else_block = build_statements(block_pair.into_inner(), file)
+                    .context("Failed to build else block")?;
+            },
+            other => bail!("Unexpected rule in conditional: {:?}", other),
+        }
+    }
+
+    // Fold the `else if` chain from the back, nesting each into the previous `else`
+    let mut statement = None;
+    for (cond, then_block, span) in branches.into_iter().rev() {
+        let else_block = match statement.take() {
+            Some(nested) => vec![nested],
+            None => std::mem::take(&mut else_block),
+        };
+        statement = Some(Statement { kind: StatementKind::If { cond, then_block, else_block }, span });
+    }
+
+    statement.context("Conditional missing 'if' branch")
+}
+
+pub fn build_choice(pair: Pair<Rule>, file: &Arc<SourceFile>) -> Result<Statement> {
+    ensure!(pair.as_rule() == Rule::choice,
+        "Expected choice, found {:?}", pair.as_rule());
+
+    let span = Span::of(&pair, file);
+    let mut prompt = None;
+    let mut options = Vec::new();
+    for inner_pair in pair.into_inner() {
+        match inner_pair.as_rule() {
+            Rule::expr => {
+                prompt = Some(build_text(inner_pair)
+                    .context("Failed to build choice prompt expression")?);
+            },
+            Rule::choice_option => {
+                let mut inner = inner_pair.into_inner();
+                let label = build_text(inner.next().context("Choice option missing label")?)
+                    .context("Failed to build choice option label")?;
+
+                let body_pair = inner.next()
+                    .context("Choice option missing block or target scene")?;
+                let block = match body_pair.as_rule() {
+                    Rule::block => build_statements(body_pair.into_inner(), file)
+                        .context("Failed to build choice option block")?,
+                    Rule::choice_target => {
+                        // A target scene is shorthand for a block that changes to it
+                        let scene_pair = body_pair.into_inner().next()
+                            .context("Choice target missing scene expression")?;
+                        let target_span = Span::of(&scene_pair, file);
+                        let scene_expr = build_expression(scene_pair)
+                            .context("Failed to build choice target expression")?;
+                        vec![Statement {
+                            kind: StatementKind::Stage(StageCommand::SceneChange { scene_expr: Box::new(scene_expr) }),
+                            span: target_span,
+                        }]
+                    },
+                    other => bail!("Unexpected rule in choice option: {:?}", other),
+                };
+
+                options.push(ChoiceOption { label, block });
+            },
+            other => bail!("Unexpected rule in choice: {:?}", other),
+        }
+    }
+
+    Ok(Statement { kind: StatementKind::Choice { prompt, options }, span })
+}
+
+pub fn build_statements(pairs: Pairs<Rule>, file: &Arc<SourceFile>) -> Result<Vec<Statement>> {
+    let mut statements = Vec::new();
+    for statement_pair in pairs {
+        let span = Span::of(&statement_pair, file);
+        let built = match statement_pair.as_rule() {
+            Rule::conditional => build_conditional(statement_pair, file)
+                .map(|stmt| vec![stmt])
+                .context("Failed to build conditional"),
+            Rule::choice => build_choice(statement_pair, file)
+                .map(|stmt| vec![stmt])
+                .context("Failed to build choice"),
+            Rule::code => build_code_statement(statement_pair, file)
+                .map(|stmt| vec![stmt])
+                .context("Failed to build code statement"),
+            Rule::stage_command => build_stage_command(statement_pair, file)
+                .map(|stmt| vec![stmt])
+                .context("Failed to build stage command"),
+            // Dialogue expands into one statement per line and stage command
+            Rule::dialogue => build_dialogue(statement_pair, file)
+                .context("Failed to build dialogue"),
+            other => Err(anyhow!("Unexpected rule in statement list: {:?}", other)),
+        };
+        let mut built = built
+            .with_context(|| span.render("...in this statement"))?;
+        statements.append(&mut built);
+    }
+    Ok(statements)
+}
+
+pub fn build_scenes(pair: Pair<Rule>, file: &Arc<SourceFile>) -> Result<Act> {
     let mut act = Act {
-        scenes: HashMap::new(),
-        entrypoint: String::new(),
+        source: file.clone(),
+        ..Default::default()
     };
     let mut first_scene_id: Option<String> = None;
     
     for scene_pair in pair.into_inner() {
         match scene_pair.as_rule() {
             Rule::scene => {
+                let span = Span::of(&scene_pair, file);
                 let mut inner_rules = scene_pair.into_inner();
                 
                 let scene_id = inner_rules.next()
@@ -364,26 +942,11 @@ pub fn build_scenes(pair: Pair<Rule>) -> Result<Act> {
                     first_scene_id = Some(scene_id.clone());
                 }
                 
-                let mut statements = Vec::new();
-                for statement_pair in inner_rules {
-                    let stmt = match statement_pair.as_rule() {
-                        Rule::code => build_code_statement(statement_pair)
-                            .context("Failed to build code statement")?,
-                        Rule::stage_command => build_stage_command(statement_pair)
-                            .context("Failed to build stage command")?,
-                        Rule::dialogue => {
-                            let mut inner_statements = build_dialogue(statement_pair)
-                                .context("Failed to build dialogue")?;
-                            statements.extend(inner_statements.drain(..));
-
-                            continue;
-                        },
-                        other => bail!("Unexpected rule in scene: {:?}", other),
-                    };
-                    statements.push(stmt);
-                }
+                let statements = build_statements(inner_rules, file)
+                    .with_context(|| format!("Failed to build statements for scene '{}'", scene_id))?;
                 
-                ensure!(act.scenes.insert(scene_id.clone(), Box::new(Scene { statements })).is_none(), "Duplicate scene ID '{}'", scene_id);
+                ensure!(act.scenes.insert(scene_id.clone(), Box::new(Scene { statements })).is_none(),
+                    "{}", span.render(format!("Duplicate scene ID '{}'", scene_id)));
             },
             Rule::EOI => continue,
             other => bail!("Unexpected rule when parsing scenes: {:?}", other),
@@ -392,4 +955,16 @@ pub fn build_scenes(pair: Pair<Rule>) -> Result<Act> {
     
     act.entrypoint = first_scene_id.context("No scenes found in act")?;
     Ok(act)
-}
\ No newline at end of file
+}
+
+// Parses and builds a whole script, with parse errors pointing into the file
+pub fn compile_act(file: &Arc<SourceFile>) -> Result<Act> {
+    let act_pair = SabiParser::parse(Rule::act, &file.text)
+        .map_err(|error| error.with_path(&file.path.to_string_lossy()))
+        .with_context(|| format!("Failed to parse script file {:?}", file.path))?
+        .next()
+        .context("Script file is empty")?;
+
+    build_scenes(act_pair, file)
+        .with_context(|| format!("Failed to build scenes from {:?}", file.path))
+}
@@ -1,5 +1,7 @@
-use crate::{BackgroundChangeMessage, CharacterSayMessage, GUIChangeMessage, CharacterChangeMessage, VisualNovelState};
-use crate::compiler::ast::{CodeStatement, Dialogue, Evaluate, StageCommand, Statement};
+use crate::audio::{AudioChangeMessage, AudioOperation};
+use crate::{BackgroundChangeMessage, CharacterSayMessage, ChoiceMessage, GUIChangeMessage, CharacterChangeMessage, HistoryEntry, VisualNovelState};
+use crate::compiler::ast::{CodeStatement, Dialogue, Evaluate, StageCommand, Statement, StatementKind};
+use crate::save::persistent::line_id;
 use bevy::prelude::*;
 use anyhow::{Context, Result};
 
@@ -14,7 +16,7 @@ pub struct ActChangeMessage {
     pub act_id: String
 }
 
-pub struct InvokeContext<'l, 'a, 'b, 'd, 'e, 'f, 'g, 'h> {
+pub struct InvokeContext<'l, 'a, 'b, 'd, 'e, 'f, 'g, 'h, 'i, 'j> {
     pub game_state: &'l mut ResMut<'a, VisualNovelState>,
     pub character_say_message: &'l mut MessageWriter<'b, CharacterSayMessage>,
     pub background_change_message: &'l mut MessageWriter<'d, BackgroundChangeMessage>,
@@ -22,16 +24,36 @@ pub struct InvokeContext<'l, 'a, 'b, 'd, 'e, 'f, 'g, 'h> {
     pub scene_change_message: &'l mut MessageWriter<'f, SceneChangeMessage>,
     pub act_change_message: &'l mut MessageWriter<'g, ActChangeMessage>,
     pub character_change_message: &'l mut MessageWriter<'h, CharacterChangeMessage>,
+    pub choice_message: &'l mut MessageWriter<'i, ChoiceMessage>,
+    pub audio_change_message: &'l mut MessageWriter<'j, AudioChangeMessage>,
 }
 pub trait Invoke {
     fn invoke ( &self, ctx: InvokeContext ) -> Result<()>;
 }
 impl Invoke for Dialogue {
     fn invoke( &self, ctx: InvokeContext ) -> Result<()> {
-        let dialogue = self.dialogue.evaluate_into_string()
+        let dialogue = self.dialogue.evaluate_into_string(&**ctx.game_state)
             .context("...while evaluating Dialogue expression")?;
+        let voice = match &self.voice {
+            Some(expr) => Some(expr.evaluate_into_string(&**ctx.game_state)
+                .context("...while evaluating Dialogue voice expression")?),
+            None => None,
+        };
         info!("Invoking Dialogue::Say");
 
+        // Always sent so the previous line's voice is cut off
+        ctx.audio_change_message.write(AudioChangeMessage {
+            operation: AudioOperation::PlayVoice(voice.clone())
+        });
+        // Skip mode can be limited to lines the player has already seen
+        let line_id = line_id(&ctx.game_state.program_counter(), self);
+        ctx.game_state.line_read = !ctx.game_state.persistent.mark_seen(line_id);
+
+        ctx.game_state.history.push(HistoryEntry {
+            name: self.character.to_owned(),
+            message: dialogue.clone(),
+            voice,
+        });
         ctx.character_say_message.write(CharacterSayMessage {
             name: self.character.to_owned(),
             message: dialogue
@@ -45,19 +67,21 @@ impl Invoke for Dialogue {
 impl Invoke for StageCommand {
     fn invoke( &self, ctx: InvokeContext ) -> Result<()> {
         match self {
-            StageCommand::BackgroundChange { background_expr } => {
-                let background_id = background_expr.evaluate_into_string()
+            StageCommand::BackgroundChange { background_expr, transition } => {
+                let background_id = background_expr.evaluate_into_string(&**ctx.game_state)
                     .context("...while evaluating BackgroundChange expression")?;
                 
-                info!("Invoking StageCommand::BackgroundChange to {}", background_id);
+                info!("Invoking StageCommand::BackgroundChange to {} with {:?}", background_id, transition);
+                // Transitions block through their tweens
                 ctx.background_change_message.write(BackgroundChangeMessage {
-                    background_id
+                    background_id,
+                    transition: transition.clone(),
                 });
             },
             StageCommand::GUIChange { id_expr, sprite_expr } => {
-                let gui_id = id_expr.evaluate_into_string()
+                let gui_id = id_expr.evaluate_into_string(&**ctx.game_state)
                     .context("...while evaluating GUIChange id expression")?;
-                let sprite_id = sprite_expr.evaluate_into_string()
+                let sprite_id = sprite_expr.evaluate_into_string(&**ctx.game_state)
                     .context("...while evaluating GUIChange sprite expression")?;
                 
                 info!("Invoking StageCommand::GUIChange to {}'s {}", gui_id, sprite_id);
@@ -67,33 +91,56 @@ impl Invoke for StageCommand {
                 });
             },
             StageCommand::SceneChange { scene_expr } => {
-                let scene_id = scene_expr.evaluate_into_string()
+                let scene_id = scene_expr.evaluate_into_string(&**ctx.game_state)
                     .context("...while evaluating SceneChange expression")?;
                 
                 info!("Invoking StageCommand::SceneChange to {}", scene_id);
                 ctx.scene_change_message.write(SceneChangeMessage {
                     scene_id
                 });
+
+                // Don't run past the change while it's still pending
+                ctx.game_state.blocking = true;
             },
             StageCommand::ActChange { act_expr } => {
-                let act_id = act_expr.evaluate_into_string()
+                let act_id = act_expr.evaluate_into_string(&**ctx.game_state)
                     .context("...while evaluating ActChange expression")?;
                 
                 info!("Invoking StageCommand::ActChange to {}", act_id);
                 ctx.act_change_message.write(ActChangeMessage {
                     act_id
                 });
+                ctx.game_state.blocking = true;
             },
             StageCommand::CharacterChange { character, operation } => {
                 info!("Invoking StageCommand::CharacterChange to {} of type {:?}", character, operation);
-                let message = CharacterChangeMessage {
+                // Fades block through their tweens, and only once they've actually started
+                ctx.character_change_message.write(CharacterChangeMessage {
                     character: character.clone(),
                     operation: operation.clone()
+                });
+            },
+            StageCommand::MusicChange { music_expr, fade } => {
+                let operation = match music_expr {
+                    Some(expr) => {
+                        let music_id = expr.evaluate_into_string(&**ctx.game_state)
+                            .context("...while evaluating MusicChange expression")?;
+                        AudioOperation::PlayMusic(music_id, *fade)
+                    },
+                    None => AudioOperation::StopMusic(*fade),
                 };
-                if message.is_blocking() {
-                    ctx.game_state.blocking = true;
-                }
-                ctx.character_change_message.write(message);
+
+                info!("Invoking StageCommand::MusicChange of type {:?}", operation);
+                ctx.audio_change_message.write(AudioChangeMessage { operation });
+            },
+            StageCommand::SoundChange { sound_expr } => {
+                let sound_id = sound_expr.evaluate_into_string(&**ctx.game_state)
+                    .context("...while evaluating SoundChange expression")?;
+
+                info!("Invoking StageCommand::SoundChange to {}", sound_id);
+                ctx.audio_change_message.write(AudioChangeMessage {
+                    operation: AudioOperation::PlaySound(sound_id)
+                });
             }
         }
         
@@ -101,13 +148,13 @@ impl Invoke for StageCommand {
     }
 }
 impl Invoke for CodeStatement {
-    fn invoke( &self, _ctx: InvokeContext ) -> Result<()> {
+    fn invoke( &self, ctx: InvokeContext ) -> Result<()> {
         match self {
             CodeStatement::Log { exprs } => {
                 let mut log_parts: Vec<String> = Vec::new();
 
                 for expr in exprs {
-                    let part = expr.evaluate_into_string()
+                    let part = expr.evaluate_into_string(&**ctx.game_state)
                         .context("...while evaluating Log expression")?;
                     log_parts.push(part);
                 }
@@ -115,6 +162,16 @@ impl Invoke for CodeStatement {
                 let log_message = log_parts.join(" ");
                 println!("[ Log ] {}", log_message);
 
+                Ok(())
+            },
+            CodeStatement::Set { variable, expr } => {
+                let value = expr.evaluate(&**ctx.game_state)
+                    .with_context(|| format!("...while evaluating Set expression for '{}'", variable))?;
+
+                info!("Invoking CodeStatement::Set of {} to {:?}", variable, value);
+                ctx.game_state.set_variable(variable, value)
+                    .context("...while assigning variable")?;
+
                 Ok(())
             },
         }
@@ -122,13 +179,44 @@ impl Invoke for CodeStatement {
 }
 impl Invoke for Statement {
     fn invoke( &self, ctx: InvokeContext ) -> Result<()> {
-        Ok(match self {
-            Statement::Dialogue(dialogue) => dialogue.invoke(ctx)
+        match &self.kind {
+            StatementKind::Dialogue(dialogue) => dialogue.invoke(ctx)
                 .context("...while invoking Dialogue statement")?,
-            Statement::Stage(stage) => stage.invoke(ctx)
+            StatementKind::Stage(stage) => stage.invoke(ctx)
                 .context("...while invoking StageCommand statement")?,
-            Statement::Code(code) => code.invoke(ctx)
+            StatementKind::Code(code) => code.invoke(ctx)
                 .context("...while invoking Code statement")?,
-        })
+            StatementKind::If { cond, .. } => {
+                let taken = cond.evaluate_into_bool(&**ctx.game_state)
+                    .context("...while evaluating If condition")?;
+
+                info!("Invoking Statement::If, taking the {} branch", if taken { "then" } else { "else" });
+                ctx.game_state.statements.enter(if taken { 0 } else { 1 })
+                    .context("...while entering If block")?;
+            },
+            StatementKind::Choice { prompt, options } => {
+                let prompt = match prompt {
+                    Some(expr) => Some(expr.evaluate_into_string(&**ctx.game_state)
+                        .context("...while evaluating Choice prompt expression")?),
+                    None => None,
+                };
+                let mut labels: Vec<String> = Vec::new();
+                for option in options {
+                    let label = option.label.evaluate_into_string(&**ctx.game_state)
+                        .context("...while evaluating Choice option expression")?;
+                    labels.push(label);
+                }
+
+                info!("Invoking Statement::Choice with {} options", labels.len());
+                ctx.choice_message.write(ChoiceMessage {
+                    prompt,
+                    options: labels
+                });
+
+                // The chosen block is entered once the player picks an option
+                ctx.game_state.blocking = true;
+            },
+        }
+        Ok(())
     }
 }
\ No newline at end of file
@@ -1,25 +1,57 @@
+use crate::audio::AudioChangeMessage;
 use crate::character::CharacterChangeMessage;
 use crate::compiler::calling::{Invoke, InvokeContext, SceneChangeMessage, ActChangeMessage};
-use crate::{BackgroundChangeMessage, CharacterSayMessage, GUIChangeMessage, VisualNovelState};
-use crate::compiler::ast::{build_scenes, Acts, Rule, SabiParser};
-use std::path::PathBuf;
+use crate::{BackgroundChangeMessage, CharacterSayMessage, ChoiceMessage, GUIChangeMessage, VisualNovelState};
+use crate::compiler::ast::{compile_act, Acts};
+use crate::compiler::diagnostics::SourceFile;
+use crate::compiler::execution::ExecutionStack;
+use crate::compiler::reload::{act_id, import_acts, ActScript, ActScriptLoader, ActScripts, ScriptErrorMessage, ScriptErrors};
+use crate::compiler::validation::validate;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use anyhow::{bail, ensure, Context, Result};
-use pest::Parser;
 
 /* States */
 #[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
-enum SabiState {
+pub enum SabiState {
     #[default]
     WaitingForControllers,
     Running,
 }
 
+/* System sets */
+// Runs the next statement. Systems that report errors with `VisualNovelState::diagnostic`
+//  go after it, so the error points at the statement that sent the message
+#[derive(SystemSet, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct ScriptSystems;
+
+// Where acts are compiled from and which one the story starts with. The acts
+//  are loaded through `reload::acts_source`, which has to be registered with the same path
+#[derive(Resource)]
+pub struct CompilerConfig {
+    pub acts_path: PathBuf,
+    // Defaults to the first act by name
+    pub entrypoint: Option<String>,
+}
+impl Default for CompilerConfig {
+    fn default() -> Self {
+        Self {
+            acts_path: PathBuf::from(".").join("assets").join("acts"),
+            entrypoint: None,
+        }
+    }
+}
+
 #[derive(Resource, Default)]
 struct ControllersReady {
     pub background_controller: bool,
     pub character_controller: bool,
     pub chat_controller: bool,
+    pub audio_controller: bool,
+    pub compiler: bool,
 }
 
 /* Messages */
@@ -27,12 +59,18 @@ struct ControllersReady {
 pub struct TriggerControllersMessage;
 #[derive(Message)]
 pub struct ControllerReadyMessage(pub Controller);
+#[derive(Message)]
+pub struct ChoiceMadeMessage {
+    pub index: usize
+}
 
 /* Custom Types */
 pub enum Controller {
     Background,
     Character,
     Chat,
+    Audio,
+    Compiler,
 }
 
 pub struct Compiler;
@@ -40,14 +78,21 @@ impl Plugin for Compiler {
     fn build(&self, app: &mut App) {
         app
             .init_state::<SabiState>()
+            .init_resource::<CompilerConfig>()
             .init_resource::<ControllersReady>()
+            .init_asset::<ActScript>()
+            .init_asset_loader::<ActScriptLoader>()
             .add_message::<ControllerReadyMessage>()
+            .add_message::<ScriptErrorMessage>()
             .add_message::<TriggerControllersMessage>()
             .add_message::<SceneChangeMessage>()
             .add_message::<ActChangeMessage>()
-            .add_systems(Startup, parse)
-            .add_systems(Update, check_states.run_if(in_state(SabiState::WaitingForControllers)))
-            .add_systems(Update, (run, handle_scene_changes, handle_act_changes).run_if(in_state(SabiState::Running)));
+            .add_message::<ChoiceMadeMessage>()
+            .add_systems(Startup, import_acts)
+            .add_systems(Update, (parse, check_states).run_if(in_state(SabiState::WaitingForControllers)))
+            .add_systems(OnEnter(SabiState::Running), validate)
+            .add_systems(Update, run.in_set(ScriptSystems).run_if(in_state(SabiState::Running)))
+            .add_systems(Update, (handle_scene_changes, handle_act_changes, handle_choices).after(ScriptSystems).run_if(in_state(SabiState::Running)));
     }
 }
 
@@ -62,12 +107,16 @@ fn check_states(
             Controller::Background => &mut controllers_state.background_controller,
             Controller::Character => &mut controllers_state.character_controller,
             Controller::Chat => &mut controllers_state.chat_controller,
+            Controller::Audio => &mut controllers_state.audio_controller,
+            Controller::Compiler => &mut controllers_state.compiler,
         };
         *controller = true;
     }
     if controllers_state.background_controller
        && controllers_state.character_controller
-       && controllers_state.chat_controller {
+       && controllers_state.chat_controller
+       && controllers_state.audio_controller
+       && controllers_state.compiler {
         msg_writer.write(TriggerControllersMessage);
         sabi_state.set(SabiState::Running);
     }
@@ -94,17 +143,14 @@ fn parse_direntry (
     
         // Compile the act
         info!("Compiling act: {}", act_name);
-        let scenes = {
-            let script_contents = std::fs::read_to_string(&file_path)
-                .with_context(|| format!("Failed to read script file: {:?}", file_path))?;
-            let scene_pair = SabiParser::parse(Rule::act, &script_contents)
-                .with_context(|| format!("Failed to parse script file: {}", act_name))?
-                .next()
-                .context("Script file is empty")?;
-            
-            build_scenes(scene_pair)
-                .context("Failed to build scenes from AST")?
-        };
+        let script_contents = std::fs::read_to_string(&file_path)
+            .with_context(|| format!("Failed to read script file: {:?}", file_path))?;
+        let source = Arc::new(SourceFile {
+            path: file_path,
+            text: script_contents,
+        });
+        let scenes = compile_act(&source)
+            .with_context(|| format!("Failed to compile act '{}'", act_name))?;
         
         ensure!(acts.insert(act_name.clone(), Box::new(scenes)).is_none(), "Duplicate act name '{}'", act_name);
         return Ok(());
@@ -122,11 +168,11 @@ fn parse_direntry (
 
     bail!("Recieved a directory entry that wasn't a file or directory (likely a symlink)!");
 }
-fn parse ( mut game_state: ResMut<VisualNovelState> ) -> Result<(), BevyError> {
-    info!("Starting parsing");
-    
+// Compiles every `.sabi` file under `acts_path`, naming each act after its file.
+//  For tools that run without an asset server; the game loads acts through `reload`
+pub fn compile_acts(acts_path: &Path) -> Result<Acts> {
     let mut acts: Acts = Acts::new();
-    for dir_entry_result in std::fs::read_dir(PathBuf::from(".").join("assets").join("acts"))
+    for dir_entry_result in std::fs::read_dir(acts_path)
         .context("...while trying to read from the scripts directory")?
     {
         let dir_entry = dir_entry_result
@@ -134,14 +180,86 @@ fn parse ( mut game_state: ResMut<VisualNovelState> ) -> Result<(), BevyError> {
         parse_direntry(&mut acts, dir_entry)
             .context("...while trying to parse a script file or directory")?;
     }
-    
-    // Setup entrypoint - use first available act and its entrypoint scene
-    let first_act_id = acts.keys().min()
-        .context("No acts found! Please ensure you have at least one `.sabi` file in the acts directory.")?
-        .clone();
+    Ok(acts)
+}
+// Builds the acts once every script in the acts folder has loaded, and sets up
+//  the entrypoint. Broken scripts are reported, and everything is compiled again
+//  whenever one of them is edited.
+fn parse(
+    mut commands: Commands,
+    mut game_state: ResMut<VisualNovelState>,
+    config: Res<CompilerConfig>,
+    act_scripts: ActScripts,
+    mut attempted: Local<bool>,
+
+    script_errors: ScriptErrors,
+    mut ready_messages: MessageWriter<ControllerReadyMessage>,
+) -> Result<(), BevyError> {
+    let ActScripts { asset_server, acts_folder, folders, scripts, mut asset_events } = act_scripts;
+    let ScriptErrors { overlay_query, mut error_messages } = script_errors;
+    let edited = asset_events.read()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }));
+    if *attempted && !edited {
+        return Ok(());
+    }
+    match asset_server.get_recursive_dependency_load_state(&acts_folder.0) {
+        Some(RecursiveDependencyLoadState::Loaded) => {},
+        Some(RecursiveDependencyLoadState::Failed(error)) => {
+            return Err(anyhow::anyhow!("Failed to load the acts in {:?}: {}", config.acts_path, error).into());
+        },
+        _ => return Ok(()),
+    }
+    *attempted = true;
+
+    info!("Starting parsing");
+    for (entity, _) in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let folder = folders.get(&acts_folder.0)
+        .context("Acts folder finished loading but is missing")?;
+    let mut acts = Acts::new();
+    let mut broken = false;
+    for handle in &folder.handles {
+        let Ok(handle) = handle.clone().try_typed::<ActScript>() else {
+            continue;
+        };
+        let act_name = act_id(&asset_server, handle.id())?;
+        let script = scripts.get(&handle)
+            .with_context(|| format!("Act '{}' finished loading but is missing", act_name))?;
+        match &script.act {
+            Ok(act) => {
+                info!("Compiled act: {}", act_name);
+                if acts.insert(act_name.clone(), Box::new(act.clone())).is_some() {
+                    return Err(anyhow::anyhow!("Duplicate act name '{}'", act_name).into());
+                }
+            },
+            Err(report) => {
+                warn!("Failed to compile act '{}'", act_name);
+                error_messages.write(ScriptErrorMessage {
+                    title: format!("Act '{}' failed to compile", act_name),
+                    act_id: act_name,
+                    report: report.clone(),
+                });
+                broken = true;
+            },
+        }
+    }
+    // Wait for the scripts to be fixed
+    if broken {
+        return Ok(());
+    }
+
+    // Setup entrypoint - use the configured act, or else the first available one
+    let first_act_id = match &config.entrypoint {
+        Some(act_id) => act_id.clone(),
+        None => acts.keys().min()
+            .context("No acts found! Please ensure you have at least one `.sabi` file in the acts directory.")?
+            .clone(),
+    };
     
     let act = acts.get(&first_act_id)
-        .context("Failed to get first act")?
+        .with_context(|| format!("Failed to get act '{}'", first_act_id))?
         .clone();
         
     let scene = act.scenes.get(&act.entrypoint)
@@ -149,32 +267,52 @@ fn parse ( mut game_state: ResMut<VisualNovelState> ) -> Result<(), BevyError> {
         .clone();
     
     game_state.acts = acts;
+    game_state.act_id = first_act_id.clone();
     game_state.act = act.clone();
+    game_state.scene_id = act.entrypoint.clone();
     game_state.scene = scene;
-    game_state.statements = game_state.scene.statements.clone().into_iter();
+    game_state.statements = ExecutionStack::new(game_state.scene.statements.clone());
     game_state.blocking = false;
     
     info!("Completed pre-compilation successfully - starting with act '{}', scene '{}'", first_act_id, act.entrypoint);
+    ready_messages.write(ControllerReadyMessage(Controller::Compiler));
     
     Ok(())
 }
 
-fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g> (
-    mut game_state: ResMut<'a, VisualNovelState>,
-    
-    mut character_say_message: MessageWriter<'b, CharacterSayMessage>,
-    mut background_change_message: MessageWriter<'c, BackgroundChangeMessage>,
-    mut gui_change_message: MessageWriter<'d, GUIChangeMessage>,
-    mut scene_change_message: MessageWriter<'e, SceneChangeMessage>,
-    mut act_change_message: MessageWriter<'f, ActChangeMessage>,
-    mut character_change_message: MessageWriter<'g, CharacterChangeMessage>,
+// Everything a statement can ask the other controllers to do
+#[derive(SystemParam)]
+struct ScriptMessages<'w> {
+    character_say_message: MessageWriter<'w, CharacterSayMessage>,
+    background_change_message: MessageWriter<'w, BackgroundChangeMessage>,
+    gui_change_message: MessageWriter<'w, GUIChangeMessage>,
+    scene_change_message: MessageWriter<'w, SceneChangeMessage>,
+    act_change_message: MessageWriter<'w, ActChangeMessage>,
+    character_change_message: MessageWriter<'w, CharacterChangeMessage>,
+    choice_message: MessageWriter<'w, ChoiceMessage>,
+    audio_change_message: MessageWriter<'w, AudioChangeMessage>,
+}
 
+fn run(
+    mut game_state: ResMut<VisualNovelState>,
+    messages: ScriptMessages,
 ) -> Result<(), BevyError> {
     if game_state.blocking {
         return Ok(());
     }
+    let ScriptMessages {
+        mut character_say_message,
+        mut background_change_message,
+        mut gui_change_message,
+        mut scene_change_message,
+        mut act_change_message,
+        mut character_change_message,
+        mut choice_message,
+        mut audio_change_message,
+    } = messages;
 
     if let Some(statement) = game_state.statements.next() {
+        game_state.current_span = Some(statement.span.clone());
         statement.invoke(InvokeContext {
                 game_state: &mut game_state,
                 character_say_message: &mut character_say_message,
@@ -183,8 +321,10 @@ fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g> (
                 scene_change_message: &mut scene_change_message,
                 act_change_message: &mut act_change_message,
                 character_change_message: &mut character_change_message,
+                choice_message: &mut choice_message,
+                audio_change_message: &mut audio_change_message,
             })
-            .context("Failed to invoke statement")?;
+            .with_context(|| statement.span.render("Failed to invoke statement"))?;
     }
 
     Ok(())
@@ -196,12 +336,13 @@ fn handle_scene_changes(
 ) -> Result<(), BevyError> {
     for msg in scene_change_messages.read() {
         let new_scene = game_state.act.scenes.get(&msg.scene_id)
-            .with_context(|| format!("Scene '{}' not found in current act", msg.scene_id))?
+            .with_context(|| game_state.diagnostic(format!("Scene '{}' not found in current act", msg.scene_id)))?
             .clone();
         
         info!("Changing to scene: {}", msg.scene_id);
+        game_state.scene_id = msg.scene_id.clone();
         game_state.scene = new_scene;
-        game_state.statements = game_state.scene.statements.clone().into_iter();
+        game_state.statements = ExecutionStack::new(game_state.scene.statements.clone());
         game_state.blocking = false;
         info!("[ Scene changed to '{}' ]", msg.scene_id);
     }
@@ -215,7 +356,7 @@ fn handle_act_changes(
 ) -> Result<(), BevyError> {
     for msg in act_change_messages.read() {
         let new_act = game_state.acts.get(&msg.act_id)
-            .with_context(|| format!("Act '{}' not found", msg.act_id))?
+            .with_context(|| game_state.diagnostic(format!("Act '{}' not found", msg.act_id)))?
             .clone();
         
         info!("Changing to act: {}", msg.act_id);
@@ -224,12 +365,28 @@ fn handle_act_changes(
             .with_context(|| format!("Entrypoint scene '{}' not found in act '{}'", new_act.entrypoint, msg.act_id))?
             .clone();
         
+        game_state.act_id = msg.act_id.clone();
         game_state.act = new_act.clone();
+        game_state.scene_id = new_act.entrypoint.clone();
         game_state.scene = entrypoint_scene;
-        game_state.statements = game_state.scene.statements.clone().into_iter();
+        game_state.statements = ExecutionStack::new(game_state.scene.statements.clone());
         game_state.blocking = false;
         info!("[ Act changed to '{}', starting at entrypoint scene '{}' ]", msg.act_id, new_act.entrypoint);
     }
     
     Ok(())
 }
+
+fn handle_choices(
+    mut choice_made_messages: MessageReader<ChoiceMadeMessage>,
+    mut game_state: ResMut<VisualNovelState>,
+) -> Result<(), BevyError> {
+    for msg in choice_made_messages.read() {
+        game_state.statements.enter(msg.index)
+            .with_context(|| format!("Choice option {} does not exist", msg.index))?;
+        game_state.blocking = false;
+        info!("[ Player chose option {} ]", msg.index);
+    }
+
+    Ok(())
+}
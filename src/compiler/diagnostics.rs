@@ -0,0 +1,78 @@
+use crate::compiler::ast::Rule;
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use pest::iterators::Pair;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+// A script as it was read from disk, kept so errors can quote it
+#[derive(Default)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub text: String,
+}
+impl fmt::Debug for SourceFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The whole script is too much noise in statement dumps
+        write!(f, "SourceFile({})", self.path.display())
+    }
+}
+
+// Where a statement starts in its script, 1-based like pest reports it
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub file: Arc<SourceFile>,
+    pub line: usize,
+    pub column: usize,
+}
+impl Span {
+    pub fn of(pair: &Pair<Rule>, file: &Arc<SourceFile>) -> Self {
+        let (line, column) = pair.line_col();
+        Self { file: file.clone(), line, column }
+    }
+
+    // Orders spans by file and then position, for reporting
+    pub fn sort_key(&self) -> (&PathBuf, usize, usize) {
+        (&self.file.path, self.line, self.column)
+    }
+
+    // Renders `message` above the offending line with a caret under the column:
+    //
+    //   Background 'x' does not exist
+    //     --> assets/acts/1.sabi:3:5
+    //      |
+    //    3 |     (Background changes to "x")
+    //      |     ^
+    pub fn render(&self, message: impl fmt::Display) -> String {
+        let source_line = self.file.text.lines()
+            .nth(self.line.saturating_sub(1))
+            .unwrap_or_default();
+        let gutter = " ".repeat(self.line.to_string().len());
+        let caret_offset: String = source_line.chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "{message}\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {source_line}\n{gutter} | {caret_offset}^",
+            self.file.path.display(), self.line, self.column, self.line,
+        )
+    }
+}
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.path.display(), self.line, self.column)
+    }
+}
+impl Serialize for Span {
+    // Only the location is written out, not the whole source file
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut span = serializer.serialize_struct("Span", 3)?;
+        span.serialize_field("file", &self.file.path)?;
+        span.serialize_field("line", &self.line)?;
+        span.serialize_field("column", &self.column)?;
+        span.end()
+    }
+}
@@ -0,0 +1,179 @@
+use crate::compiler::ast::{Act, Statement};
+use crate::VisualNovelState;
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/* Custom Types */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FramePosition {
+    // Which block of the enclosing statement this frame runs (always 0 for the scene itself)
+    pub branch: usize,
+    // Index of the next statement to run within the block
+    pub index: usize,
+}
+
+// Where execution is, in a form that can be written to disk and restored
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProgramCounter {
+    pub act: String,
+    pub scene: String,
+    // Outermost frame first
+    pub frames: Vec<FramePosition>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Frame {
+    statements: Vec<Statement>,
+    position: FramePosition,
+}
+
+// Tracks the statements left to run, one frame per nested block
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionStack {
+    frames: Vec<Frame>,
+}
+
+impl ExecutionStack {
+    pub fn new(statements: Vec<Statement>) -> Self {
+        Self {
+            frames: vec![Frame { statements, position: FramePosition::default() }],
+        }
+    }
+
+    // Rebuilds a stack from a scene's statements and previously saved positions
+    pub fn restore(statements: Vec<Statement>, positions: &[FramePosition]) -> Result<Self> {
+        let mut stack = Self::new(statements);
+        let Some((first, rest)) = positions.split_first() else {
+            return Ok(stack);
+        };
+
+        stack.set_index(first.index)?;
+        for position in rest {
+            stack.enter(position.branch)
+                .context("Saved position no longer matches the script")?;
+            stack.set_index(position.index)?;
+        }
+        Ok(stack)
+    }
+
+    fn set_index(&mut self, index: usize) -> Result<()> {
+        let frame = self.frames.last_mut()
+            .context("No statements are running")?;
+        ensure!(index <= frame.statements.len(),
+            "Saved position {} is past the end of a block of {} statements", index, frame.statements.len());
+        frame.position.index = index;
+        Ok(())
+    }
+
+    pub fn positions(&self) -> Vec<FramePosition> {
+        self.frames.iter()
+            .map(|frame| frame.position.clone())
+            .collect()
+    }
+
+    // Enters a block of the statement that was just run, which runs before
+    //  the rest of the current one
+    pub fn enter(&mut self, branch: usize) -> Result<()> {
+        let frame = self.frames.last()
+            .context("No statements are running")?;
+        let statement = frame.position.index.checked_sub(1)
+            .and_then(|index| frame.statements.get(index))
+            .context("No statement has run in the current block")?;
+        let statements = statement.block(branch)
+            .with_context(|| format!("Statement has no block for branch {}", branch))?
+            .clone();
+
+        self.frames.push(Frame { statements, position: FramePosition { branch, index: 0 } });
+        Ok(())
+    }
+
+    // Whether every block has run to completion
+    pub fn is_finished(&self) -> bool {
+        self.frames.iter()
+            .all(|frame| frame.position.index >= frame.statements.len())
+    }
+
+}
+impl Iterator for ExecutionStack {
+    type Item = Statement;
+
+    // Advances to the next statement, leaving any finished blocks
+    fn next(&mut self) -> Option<Statement> {
+        while let Some(frame) = self.frames.last_mut() {
+            if let Some(statement) = frame.statements.get(frame.position.index) {
+                frame.position.index += 1;
+                return Some(statement.clone());
+            }
+            self.frames.pop();
+        }
+        None
+    }
+}
+
+impl VisualNovelState {
+    // Renders an error against the statement that ran last, when there is one
+    pub fn diagnostic(&self, message: impl std::fmt::Display) -> String {
+        match &self.current_span {
+            Some(span) => span.render(message),
+            None => message.to_string(),
+        }
+    }
+
+    // Whether the story has nothing left to run or wait on
+    pub fn is_finished(&self) -> bool {
+        !self.blocking && self.statements.is_finished()
+    }
+
+    pub fn program_counter(&self) -> ProgramCounter {
+        ProgramCounter {
+            act: self.act_id.clone(),
+            scene: self.scene_id.clone(),
+            frames: self.statements.positions(),
+        }
+    }
+
+    // Jumps execution to a previously saved program counter
+    pub fn restore_program_counter(&mut self, program_counter: &ProgramCounter) -> Result<()> {
+        let act = self.acts.get(&program_counter.act)
+            .with_context(|| format!("Act '{}' not found", program_counter.act))?
+            .clone();
+        let scene = act.scenes.get(&program_counter.scene)
+            .with_context(|| format!("Scene '{}' not found in act '{}'", program_counter.scene, program_counter.act))?
+            .clone();
+
+        self.statements = ExecutionStack::restore(scene.statements.clone(), &program_counter.frames)
+            .with_context(|| format!("Failed to restore position in scene '{}'", program_counter.scene))?;
+        self.act_id = program_counter.act.clone();
+        self.act = act;
+        self.scene_id = program_counter.scene.clone();
+        self.scene = scene;
+        self.current_span = None;
+        Ok(())
+    }
+
+    // Swaps in a recompiled act. Whatever is running keeps its place if the new
+    //  script still has it, or else starts the scene over. Returns whether it restarted.
+    pub fn replace_act(&mut self, act_id: &str, act: Box<Act>) -> bool {
+        self.acts.insert(act_id.to_owned(), act);
+        if act_id != self.act_id {
+            return false;
+        }
+
+        let program_counter = self.program_counter();
+        if self.restore_program_counter(&program_counter).is_ok() {
+            return false;
+        }
+
+        // The scene itself may be gone, in which case the act starts over
+        let act = self.acts[act_id].clone();
+        let scene_id = if act.scenes.contains_key(&self.scene_id) { self.scene_id.clone() } else { act.entrypoint.clone() };
+        self.scene = act.scenes[&scene_id].clone();
+        self.statements = ExecutionStack::new(self.scene.statements.clone());
+        self.scene_id = scene_id;
+        self.act = act;
+        self.current_span = None;
+        self.blocking = false;
+        true
+    }
+}
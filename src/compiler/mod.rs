@@ -1,5 +1,9 @@
 pub mod controller;
 pub mod ast;
 pub mod calling;
+pub mod diagnostics;
+pub mod execution;
+pub mod reload;
+pub mod validation;
 
 pub use controller::Compiler;
\ No newline at end of file
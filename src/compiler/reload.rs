@@ -0,0 +1,261 @@
+use crate::compiler::ast::{compile_act, Act};
+use crate::compiler::controller::{CompilerConfig, SabiState};
+use crate::compiler::diagnostics::SourceFile;
+use crate::compiler::validation::{render_errors, validate_acts, LoadedAssets};
+use crate::{Object, VisualNovelState};
+use crate::chat::ChoiceMenu;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use bevy::asset::io::{AssetSource, AssetSourceBuilder};
+use bevy::asset::{AssetLoader, LoadedFolder};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use thiserror::Error;
+
+// Acts are read through their own asset source, so `CompilerConfig::acts_path`
+//  doesn't have to be inside the `assets` folder
+pub const ACTS_SOURCE: &str = "acts";
+
+// Must be registered before `AssetPlugin`, so before `DefaultPlugins`
+pub fn acts_source(acts_path: &Path) -> AssetSourceBuilder {
+    let path = acts_path.to_string_lossy().into_owned();
+    AssetSource::build()
+        .with_reader(AssetSource::get_default_reader(path.clone()))
+        .with_watcher(AssetSource::get_default_watcher(path, Duration::from_millis(300)))
+}
+
+/* Assets */
+// A compiled `.sabi` file. Broken scripts still load, so the error can be shown
+//  on screen while the game keeps running the last good version.
+#[derive(Asset, TypePath)]
+pub struct ActScript {
+    pub act: Result<Act, String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ActScriptError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Script is not valid UTF-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+}
+
+// Diagnostics name scripts by their path on disk, not inside the asset source
+pub struct ActScriptLoader {
+    acts_path: PathBuf,
+}
+impl FromWorld for ActScriptLoader {
+    fn from_world(world: &mut World) -> Self {
+        let acts_path = match world.get_resource::<CompilerConfig>() {
+            Some(config) => config.acts_path.clone(),
+            None => CompilerConfig::default().acts_path,
+        };
+        Self { acts_path }
+    }
+}
+impl AssetLoader for ActScriptLoader {
+    type Asset = ActScript;
+    type Settings = ();
+    type Error = ActScriptError;
+
+    fn load(
+            &self,
+            reader: &mut dyn bevy::asset::io::Reader,
+            _settings: &Self::Settings,
+            load_context: &mut bevy::asset::LoadContext,
+        ) -> impl bevy::tasks::ConditionalSendFuture<Output = std::result::Result<Self::Asset, Self::Error>> {
+        let path = self.acts_path.join(load_context.path());
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let source = Arc::new(SourceFile {
+                path,
+                text: String::from_utf8(bytes)?,
+            });
+            let act = compile_act(&source)
+                .map_err(|error| format!("{:?}", error));
+            Ok(ActScript { act })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sabi"]
+    }
+}
+
+/* Messages */
+// An act that failed to compile or validate
+#[derive(Message)]
+pub struct ScriptErrorMessage {
+    pub act_id: String,
+    pub title: String,
+    pub report: String,
+}
+
+/* Components */
+#[derive(Component)]
+pub struct ScriptErrorOverlay {
+    pub act_id: String,
+}
+
+/* Resources */
+#[derive(Resource)]
+pub struct HandleToActsFolder(pub Handle<LoadedFolder>);
+
+/* Custom types */
+// The acts folder, the scripts loaded from it and their edits
+#[derive(SystemParam)]
+pub struct ActScripts<'w, 's> {
+    pub asset_server: Res<'w, AssetServer>,
+    pub acts_folder: Res<'w, HandleToActsFolder>,
+    pub folders: Res<'w, Assets<LoadedFolder>>,
+    pub scripts: Res<'w, Assets<ActScript>>,
+    pub asset_events: MessageReader<'w, 's, AssetEvent<ActScript>>,
+}
+// Broken scripts, and the overlays already showing them
+#[derive(SystemParam)]
+pub struct ScriptErrors<'w, 's> {
+    pub overlay_query: Query<'w, 's, (Entity, &'static ScriptErrorOverlay)>,
+    pub error_messages: MessageWriter<'w, ScriptErrorMessage>,
+}
+
+// Recompiles acts as their files change on disk, without restarting the game,
+//  and shows any script errors on screen
+pub struct ActReloader;
+impl Plugin for ActReloader {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, reload_acts.run_if(in_state(SabiState::Running)))
+            .add_systems(Update, show_script_errors.after(reload_acts));
+    }
+}
+pub fn import_acts(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // Holding the folder keeps every script loaded, and so watched
+    let loaded_folder = asset_server.load_folder(format!("{}://", ACTS_SOURCE));
+    commands.insert_resource(HandleToActsFolder(loaded_folder));
+}
+// Acts are named after their file, wherever they are in the acts folder
+pub fn act_id(asset_server: &AssetServer, id: AssetId<ActScript>) -> Result<String> {
+    Ok(asset_server.get_path(id)
+        .context("Act has no path")?
+        .path()
+        .file_stem()
+        .context("Act has no file name")?
+        .to_string_lossy()
+        .into_owned())
+}
+fn show_script_errors(
+    mut commands: Commands,
+    mut error_messages: MessageReader<ScriptErrorMessage>,
+    asset_server: Res<AssetServer>,
+    overlay_query: Query<(Entity, &ScriptErrorOverlay)>,
+) {
+    for msg in error_messages.read() {
+        for (entity, overlay) in overlay_query.iter() {
+            if overlay.act_id == msg.act_id {
+                commands.entity(entity).despawn();
+            }
+        }
+        show_error_overlay(&mut commands, &asset_server, msg);
+    }
+}
+fn show_error_overlay(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    msg: &ScriptErrorMessage,
+) {
+    commands.spawn((
+        ScriptErrorOverlay { act_id: msg.act_id.clone() },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(0.),
+            left: Val::Px(0.),
+            width: Val::Percent(100.),
+            padding: UiRect::all(Val::Px(16.)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.35, 0.05, 0.05, 0.92)),
+        GlobalZIndex(100),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::new(format!("{}\n\n{}", msg.title, msg.report)),
+            TextFont {
+                font: asset_server.load("fonts/ALLER.ttf"),
+                font_size: 16.,
+                ..default()
+            },
+            TextColor::WHITE,
+        ));
+    });
+}
+fn reload_acts(
+    mut commands: Commands,
+    act_scripts: ActScripts,
+    mut game_state: ResMut<VisualNovelState>,
+    script_errors: ScriptErrors,
+
+    loaded_assets: LoadedAssets,
+    choice_query: Query<Entity, With<ChoiceMenu>>,
+    mut visibility_query: Query<(&Object, &mut Visibility)>,
+) -> Result<(), BevyError> {
+    let ActScripts { asset_server, scripts, mut asset_events, .. } = act_scripts;
+    let ScriptErrors { overlay_query, mut error_messages } = script_errors;
+    for event in asset_events.read() {
+        // The first load is already compiled by `parse`, so only edits matter
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let Some(script) = scripts.get(*id) else {
+            continue;
+        };
+        let act_id = act_id(&asset_server, *id)
+            .context("Failed to name a reloaded act")?;
+
+        for (entity, overlay) in overlay_query.iter() {
+            if overlay.act_id == act_id {
+                commands.entity(entity).despawn();
+            }
+        }
+
+        let title = format!("Act '{}' was not reloaded", act_id);
+        let act = match &script.act {
+            Ok(act) => act,
+            Err(report) => {
+                warn!("Failed to reload act '{}'", act_id);
+                error_messages.write(ScriptErrorMessage { act_id, title, report: report.clone() });
+                continue;
+            }
+        };
+
+        // Check the new version against everything else before swapping it in
+        let mut acts = game_state.acts.clone();
+        acts.insert(act_id.clone(), Box::new(act.clone()));
+        let catalog = loaded_assets.catalog(&game_state);
+        let errors = validate_acts(&acts, &catalog);
+        if !errors.is_empty() {
+            warn!("Reloaded act '{}' has {} problem(s)", act_id, errors.len());
+            error_messages.write(ScriptErrorMessage { act_id, title, report: render_errors(&errors) });
+            continue;
+        }
+
+        if game_state.replace_act(&act_id, Box::new(act.clone())) {
+            // The scene starts over, so whatever it was showing goes away
+            for entity in choice_query.iter() {
+                commands.entity(entity).despawn();
+            }
+            for (obj, mut visibility) in visibility_query.iter_mut() {
+                if obj.id == "_textbox_background" {
+                    *visibility = Visibility::Hidden;
+                }
+            }
+            info!("[ Reloaded act '{}', restarting scene '{}' ]", act_id, game_state.scene_id);
+        } else {
+            info!("[ Reloaded act '{}' ]", act_id);
+        }
+    }
+    Ok(())
+}
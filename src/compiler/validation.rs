@@ -0,0 +1,344 @@
+use crate::background::{Background, TransitionEffect};
+use crate::chat::RichText;
+use crate::character::{CharacterConfig, CharacterOperation, CharactersConfig, CharactersResource, Configs};
+use crate::compiler::ast::{Act, Acts, Evaluate, Expr, StageCommand, Statement, StatementKind, Variables};
+use crate::compiler::diagnostics::Span;
+use crate::compiler::reload::ScriptErrorMessage;
+use crate::VisualNovelState;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/* Custom types */
+// Asset ids the scripts are checked against; a `None` category wasn't loaded and is skipped
+#[derive(Debug, Clone, Default)]
+pub struct AssetCatalog {
+    pub backgrounds: Option<HashSet<String>>,
+    pub transition_masks: Option<HashSet<String>>,
+    pub gui_sprites: Option<HashSet<String>>,
+    pub characters: Option<CharactersConfig>,
+    // (character, outfit, emotion) for every sprite that exists
+    pub character_sprites: Option<HashSet<(String, String, String)>>,
+}
+
+impl AssetCatalog {
+    // Reads asset ids straight from an assets folder laid out like `assets/`,
+    //  for checking scripts without starting the game
+    pub fn from_dir(assets_path: &Path) -> Result<Self> {
+        let mut characters = CharactersConfig::new();
+        let mut character_sprites = HashSet::new();
+        for character_entry in read_dir_sorted(&assets_path.join("characters"))? {
+            if !character_entry.is_dir() {
+                continue;
+            }
+            for entry in read_dir_sorted(&character_entry)? {
+                if entry.is_dir() {
+                    let outfit = file_stem(&entry)?;
+                    for emotion in file_stems(&entry)? {
+                        character_sprites.insert((file_stem(&character_entry)?, outfit.clone(), emotion));
+                    }
+                } else if entry.extension().is_some_and(|ext| ext == "json") {
+                    let contents = std::fs::read(&entry)
+                        .with_context(|| format!("Failed to read character config {:?}", entry))?;
+                    let config: CharacterConfig = serde_json::from_slice(&contents)
+                        .with_context(|| format!("Failed to parse character config {:?}", entry))?;
+                    characters.insert(file_stem(&character_entry)?, config);
+                }
+            }
+        }
+
+        Ok(Self {
+            backgrounds: Some(file_stems(&assets_path.join("backgrounds"))?),
+            transition_masks: Some(file_stems(&assets_path.join("transitions"))?),
+            gui_sprites: Some(file_stems(&assets_path.join("gui"))?),
+            characters: Some(characters),
+            character_sprites: Some(character_sprites),
+        })
+    }
+}
+fn read_dir_sorted(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(path)
+        .with_context(|| format!("Failed to read asset directory {:?}", path))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .with_context(|| format!("Failed to read an entry of {:?}", path))?;
+    entries.sort();
+    Ok(entries)
+}
+fn file_stem(path: &Path) -> Result<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .with_context(|| format!("Asset file has no name: {:?}", path))
+}
+fn file_stems(path: &Path) -> Result<HashSet<String>> {
+    read_dir_sorted(path)?
+        .iter()
+        // Dotfiles like `.gitkeep` aren't assets
+        .filter(|entry| !entry.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')))
+        .map(|entry| file_stem(entry))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    pub span: Span,
+    pub message: String,
+}
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+struct Validator<'a> {
+    acts: &'a Acts,
+    catalog: &'a AssetCatalog,
+    act_id: &'a str,
+    act: &'a Act,
+    // Characters whose outfit was already checked, so it's only reported once
+    checked_outfits: &'a mut HashSet<String>,
+    // Likewise for the GUI sprites a speaker's style asks for
+    checked_speakers: &'a mut HashSet<String>,
+    errors: &'a mut Vec<ValidationError>,
+}
+
+// Checks every act for targets and asset ids that would fail once the story reaches them
+pub fn validate_acts(acts: &Acts, catalog: &AssetCatalog) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let mut checked_outfits = HashSet::new();
+    let mut checked_speakers = HashSet::new();
+
+    for (act_id, act) in acts {
+        let mut validator = Validator {
+            acts,
+            catalog,
+            act_id,
+            act,
+            checked_outfits: &mut checked_outfits,
+            checked_speakers: &mut checked_speakers,
+            errors: &mut errors,
+        };
+        for scene in act.scenes.values() {
+            validator.validate_block(&scene.statements);
+        }
+    }
+
+    errors.sort_by(|a, b| a.span.sort_key().cmp(&b.span.sort_key()));
+    errors
+}
+
+// Only expressions without variables can be known before running
+fn constant(expr: &Expr) -> Option<String> {
+    expr.evaluate_into_string(&Variables::default()).ok()
+}
+
+impl Validator<'_> {
+    fn report(&mut self, span: &Span, message: String) {
+        self.errors.push(ValidationError {
+            span: span.clone(),
+            message,
+        });
+    }
+
+    fn validate_block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.validate_statement(statement);
+        }
+    }
+
+    fn validate_statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Stage(stage) => self.validate_stage_command(stage, &statement.span),
+            StatementKind::If { then_block, else_block, .. } => {
+                self.validate_block(then_block);
+                self.validate_block(else_block);
+            },
+            StatementKind::Choice { options, .. } => {
+                for option in options {
+                    self.validate_block(&option.block);
+                }
+            },
+            StatementKind::Dialogue(dialogue) => {
+                self.validate_speaker(&dialogue.character, &statement.span);
+                if let Some(line) = constant(&dialogue.dialogue)
+                    && let Err(error) = RichText::parse(&line) {
+                    self.report(&statement.span, format!("Invalid markup: {}", error));
+                }
+            },
+            StatementKind::Code(_) => {},
+        }
+    }
+
+    fn validate_stage_command(&mut self, stage: &StageCommand, span: &Span) {
+        match stage {
+            StageCommand::BackgroundChange { background_expr, transition } => {
+                if let (Some(background_id), Some(backgrounds)) = (constant(background_expr), &self.catalog.backgrounds)
+                    && !backgrounds.contains(&background_id) {
+                    self.report(span, format!("Background '{}' does not exist", background_id));
+                }
+                if let (Some(TransitionEffect::Dissolve(Some(mask_id))), Some(masks)) = (transition.as_ref().map(|transition| &transition.effect), &self.catalog.transition_masks)
+                    && !masks.contains(mask_id) {
+                    self.report(span, format!("Transition mask '{}' does not exist", mask_id));
+                }
+            },
+            StageCommand::GUIChange { sprite_expr, .. } => {
+                if let (Some(sprite_id), Some(gui_sprites)) = (constant(sprite_expr), &self.catalog.gui_sprites)
+                    && !gui_sprites.contains(&sprite_id) {
+                    self.report(span, format!("GUI sprite '{}' does not exist", sprite_id));
+                }
+            },
+            StageCommand::SceneChange { scene_expr } => {
+                if let Some(scene_id) = constant(scene_expr)
+                    && !self.act.scenes.contains_key(&scene_id) {
+                    self.report(span, format!("Scene '{}' does not exist in act '{}'", scene_id, self.act_id));
+                }
+            },
+            StageCommand::ActChange { act_expr } => {
+                if let Some(act_id) = constant(act_expr)
+                    && !self.acts.contains_key(&act_id) {
+                    self.report(span, format!("Act '{}' does not exist", act_id));
+                }
+            },
+            StageCommand::CharacterChange { character, operation } => {
+                self.validate_character(character, operation, span);
+            },
+            StageCommand::MusicChange { .. } | StageCommand::SoundChange { .. } => {},
+        }
+    }
+
+    fn validate_speaker(&mut self, character: &str, span: &Span) {
+        // MC is the player, who has no config and uses the chat theme
+        let Some(characters) = &self.catalog.characters else {
+            return;
+        };
+        if character == "MC" {
+            return;
+        }
+        let Some(config) = characters.get(character) else {
+            self.report(span, format!("Character '{}' does not exist", character));
+            return;
+        };
+        if !self.checked_speakers.insert(character.to_owned()) {
+            return;
+        }
+        let Some(gui_sprites) = &self.catalog.gui_sprites else {
+            return;
+        };
+        let missing: Vec<String> = [&config.textbox, &config.namebox].into_iter()
+            .flatten()
+            .filter(|sprite_id| !gui_sprites.contains(*sprite_id))
+            .cloned()
+            .collect();
+        for sprite_id in missing {
+            self.report(span, format!("Character '{}' uses GUI sprite '{}', which does not exist", character, sprite_id));
+        }
+    }
+
+    fn validate_character(&mut self, character: &str, operation: &CharacterOperation, span: &Span) {
+        let Some(characters) = &self.catalog.characters else {
+            return;
+        };
+        let Some(config) = characters.get(character) else {
+            self.report(span, format!("Character '{}' does not exist", character));
+            return;
+        };
+
+        if !self.checked_outfits.contains(character) {
+            self.checked_outfits.insert(character.to_owned());
+            if !config.outfits.contains(&config.outfit) {
+                self.report(span, format!("Character '{}' has no outfit '{}'", character, config.outfit));
+            }
+        }
+
+        let emotion = match operation {
+            CharacterOperation::Spawn(Some(emotion), _, _) | CharacterOperation::EmotionChange(emotion) => emotion,
+            CharacterOperation::Spawn(None, _, _) => &config.emotion,
+            CharacterOperation::OutfitChange(outfit, _) => return self.validate_outfit(config, outfit, span),
+            CharacterOperation::Despawn(_) | CharacterOperation::Move(..) => return,
+        };
+        if !config.emotions.contains(emotion) {
+            self.report(span, format!("Character '{}' has no emotion '{}'", character, emotion));
+            return;
+        }
+        if let Some(sprites) = &self.catalog.character_sprites {
+            let key = (config.name.clone(), config.outfit.clone(), emotion.clone());
+            if !sprites.contains(&key) {
+                self.report(span, format!("Character '{}' has no sprite for emotion '{}' in outfit '{}'", character, emotion, config.outfit));
+            }
+        }
+    }
+
+    fn validate_outfit(&mut self, config: &CharacterConfig, outfit: &str, span: &Span) {
+        if !config.outfits.iter().any(|listed| listed == outfit) {
+            self.report(span, format!("Character '{}' has no outfit '{}'", config.name, outfit));
+            return;
+        }
+        if let Some(sprites) = &self.catalog.character_sprites
+            && !sprites.iter().any(|(character, sprite_outfit, _)| *character == config.name && sprite_outfit == outfit) {
+            self.report(span, format!("Character '{}' has no sprites for outfit '{}'", config.name, outfit));
+        }
+    }
+}
+
+// Whatever the running game has loaded, for checking scripts against
+#[derive(SystemParam)]
+pub struct LoadedAssets<'w, 's> {
+    background_query: Query<'w, 's, &'static Background>,
+    configs: Option<Res<'w, Configs>>,
+    sprites: Option<Res<'w, CharactersResource>>,
+}
+impl LoadedAssets<'_, '_> {
+    pub fn catalog(&self, game_state: &VisualNovelState) -> AssetCatalog {
+        AssetCatalog {
+            backgrounds: self.background_query.single().ok()
+                .map(|background| background.background_sprites.keys().cloned().collect()),
+            transition_masks: self.background_query.single().ok()
+                .map(|background| background.mask_sprites.keys().cloned().collect()),
+            // Nothing is loaded when running without the chat controller
+            gui_sprites: (!game_state.gui_sprites.is_empty())
+                .then(|| game_state.gui_sprites.keys().cloned().collect()),
+            characters: self.configs.as_ref().map(|configs| configs.0.clone()),
+            character_sprites: self.sprites.as_ref().map(|sprites| {
+                sprites.0.keys()
+                    .map(|key| (key.character.clone(), key.outfit.clone(), key.emotion.clone()))
+                    .collect()
+            }),
+        }
+    }
+}
+
+pub fn render_errors(errors: &[ValidationError]) -> String {
+    let report = errors.iter()
+        .map(|error| error.span.render(&error.message))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!("Found {} problem(s) in the scripts:\n\n{}", errors.len(), report)
+}
+
+// Runs once every controller has loaded its assets, before the first statement
+// Problems are shown on screen and hold the story, instead of crashing the game
+pub fn validate(
+    mut game_state: ResMut<VisualNovelState>,
+    loaded_assets: LoadedAssets,
+    mut error_messages: MessageWriter<ScriptErrorMessage>,
+) {
+    let catalog = loaded_assets.catalog(&game_state);
+    let errors = validate_acts(&game_state.acts, &catalog);
+    if !errors.is_empty() {
+        error_messages.write(ScriptErrorMessage {
+            // Not about any one act, so reloading an act leaves it up
+            act_id: String::new(),
+            title: String::from("The story can't start until these are fixed and the game restarted"),
+            report: render_errors(&errors),
+        });
+        game_state.blocking = true;
+        return;
+    }
+
+    info!("Validated {} act(s) with no problems", game_state.acts.len());
+}
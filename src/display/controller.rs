@@ -0,0 +1,105 @@
+use crate::input::{Action, ActionState};
+
+use bevy::camera::{ScalingMode, Viewport};
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowMode, WindowResized};
+
+// Everything is laid out for this size, then scaled to fit the window
+pub const DESIGN_RESOLUTION: Vec2 = Vec2::new(1280., 800.);
+
+/* Custom types */
+// Largest rect with the design aspect ratio that fits the window, centered,
+//  in the same units as the window size it was computed from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Letterbox {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+impl Letterbox {
+    pub fn fit(window_size: Vec2) -> Self {
+        let scale = (window_size / DESIGN_RESOLUTION).min_element();
+        let size = DESIGN_RESOLUTION * scale;
+        Self {
+            position: ((window_size - size) / 2.).floor(),
+            size,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.size.x / DESIGN_RESOLUTION.x
+    }
+
+    // Design-space position under a window position, with the origin at the center
+    //  and y up like the world; None over the bars
+    pub fn to_design(&self, window_position: Vec2) -> Option<Vec2> {
+        let local = window_position - self.position;
+        if local.cmplt(Vec2::ZERO).any() || local.cmpgt(self.size).any() {
+            return None;
+        }
+        let design = local / self.scale() - DESIGN_RESOLUTION / 2.;
+        Some(Vec2::new(design.x, -design.y))
+    }
+}
+
+pub struct DisplayController;
+impl Plugin for DisplayController {
+    fn build(&self, app: &mut App) {
+        // Shows in the bars around the letterboxed view
+        app.insert_resource(ClearColor(Color::BLACK))
+            .add_systems(Update, (toggle_fullscreen, fit_camera).chain());
+    }
+}
+
+fn toggle_fullscreen(
+    actions: Res<ActionState>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !actions.triggered(Action::Fullscreen) {
+        return;
+    }
+    let Ok(mut window) = window.single_mut() else {
+        return;
+    };
+    window.mode = match window.mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+        _ => WindowMode::Windowed,
+    };
+}
+// Keeps the world and the UI at the design resolution, scaled into the window
+fn fit_camera(
+    mut resize_events: MessageReader<WindowResized>,
+    added_query: Query<(), Added<Camera2d>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&mut Camera, &mut Projection), With<Camera2d>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let resized = resize_events.read().count() > 0;
+    if !resized && added_query.is_empty() {
+        return;
+    }
+    let Ok(window) = window.single() else {
+        return;
+    };
+    let physical_size = window.physical_size().as_vec2();
+    if physical_size.min_element() <= 0. {
+        // Minimized
+        return;
+    }
+
+    let letterbox = Letterbox::fit(physical_size);
+    for (mut camera, mut projection) in camera_query.iter_mut() {
+        camera.viewport = Some(Viewport {
+            physical_position: letterbox.position.as_uvec2(),
+            physical_size: letterbox.size.as_uvec2().max(UVec2::ONE),
+            ..default()
+        });
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            orthographic.scaling_mode = ScalingMode::Fixed {
+                width: DESIGN_RESOLUTION.x,
+                height: DESIGN_RESOLUTION.y,
+            };
+        }
+    }
+    // UI pixel values are design pixels too
+    ui_scale.0 = letterbox.scale() / window.scale_factor();
+}
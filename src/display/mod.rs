@@ -0,0 +1,5 @@
+pub mod controller;
+
+pub use controller::DisplayController;
+pub use controller::Letterbox;
+pub use controller::DESIGN_RESOLUTION;
@@ -0,0 +1,155 @@
+use crate::audio::{AudioChangeMessage, AudioOperation};
+use crate::compiler::calling::{ActChangeMessage, SceneChangeMessage};
+use crate::compiler::controller::{ChoiceMadeMessage, Controller, ControllerReadyMessage, ScriptSystems};
+use crate::compiler::reload::ScriptErrorMessage;
+use crate::{BackgroundChangeMessage, CharacterChangeMessage, CharacterOperation, CharacterSayMessage, ChoiceMessage, GUIChangeMessage, VisualNovelState};
+
+use std::collections::VecDeque;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/* Custom types */
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranscriptEntry {
+    Say { name: String, message: String },
+    Background(String),
+    GUI { gui_id: String, sprite_id: String },
+    Character { character: String, operation: CharacterOperation },
+    Scene(String),
+    Act(String),
+    Choice { prompt: Option<String>, options: Vec<String>, chosen: usize },
+    Audio(AudioOperation),
+}
+
+/* Resources */
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct Transcript(pub Vec<TranscriptEntry>);
+impl Transcript {
+    // Just the dialogue, as `(speaker, line)` pairs
+    pub fn lines(&self) -> Vec<(&str, &str)> {
+        self.0.iter()
+            .filter_map(|entry| match entry {
+                TranscriptEntry::Say { name, message } => Some((name.as_str(), message.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+#[derive(Resource, Default)]
+pub struct ScriptedChoices(pub VecDeque<usize>);
+#[derive(Resource, Default)]
+pub struct HeadlessError(pub Option<String>);
+
+// Stands in for every asset-backed controller, answering the compiler
+//  immediately and recording what it was asked to show
+pub struct HeadlessController;
+impl Plugin for HeadlessController {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Transcript>()
+            .init_resource::<ScriptedChoices>()
+            .init_resource::<HeadlessError>()
+            .add_message::<BackgroundChangeMessage>()
+            .add_message::<CharacterChangeMessage>()
+            .add_message::<CharacterSayMessage>()
+            .add_message::<GUIChangeMessage>()
+            .add_message::<ChoiceMessage>()
+            .add_message::<AudioChangeMessage>()
+            .add_systems(Startup, report_ready)
+            .add_systems(Update, (record_stage, record_dialogue, answer_choices).chain().after(ScriptSystems))
+            .add_systems(Update, record_script_errors);
+    }
+}
+fn report_ready(mut msg_writer: MessageWriter<ControllerReadyMessage>) {
+    msg_writer.write(ControllerReadyMessage(Controller::Background));
+    msg_writer.write(ControllerReadyMessage(Controller::Character));
+    msg_writer.write(ControllerReadyMessage(Controller::Chat));
+    msg_writer.write(ControllerReadyMessage(Controller::Audio));
+}
+// There's no screen to show broken scripts on, so the run fails instead
+fn record_script_errors(
+    mut error_messages: MessageReader<ScriptErrorMessage>,
+    mut error: ResMut<HeadlessError>,
+) {
+    for msg in error_messages.read() {
+        error.0 = Some(format!("{}\n\n{}", msg.title, msg.report));
+    }
+}
+// Every stage direction the script can send
+#[derive(SystemParam)]
+struct StageMessages<'w, 's> {
+    background_change_message: MessageReader<'w, 's, BackgroundChangeMessage>,
+    gui_change_message: MessageReader<'w, 's, GUIChangeMessage>,
+    character_change_message: MessageReader<'w, 's, CharacterChangeMessage>,
+    audio_change_message: MessageReader<'w, 's, AudioChangeMessage>,
+    scene_change_message: MessageReader<'w, 's, SceneChangeMessage>,
+    act_change_message: MessageReader<'w, 's, ActChangeMessage>,
+}
+
+fn record_stage(
+    mut transcript: ResMut<Transcript>,
+    messages: StageMessages,
+) {
+    let StageMessages {
+        mut background_change_message,
+        mut gui_change_message,
+        mut character_change_message,
+        mut audio_change_message,
+        mut scene_change_message,
+        mut act_change_message,
+    } = messages;
+    for msg in background_change_message.read() {
+        transcript.0.push(TranscriptEntry::Background(msg.background_id.clone()));
+    }
+    for msg in gui_change_message.read() {
+        transcript.0.push(TranscriptEntry::GUI { gui_id: msg.gui_id.clone(), sprite_id: msg.sprite_id.clone() });
+    }
+    for msg in character_change_message.read() {
+        transcript.0.push(TranscriptEntry::Character { character: msg.character.clone(), operation: msg.operation.clone() });
+    }
+    for msg in audio_change_message.read() {
+        // Every line stops the last voice clip, which isn't worth recording
+        if msg.operation != AudioOperation::PlayVoice(None) {
+            transcript.0.push(TranscriptEntry::Audio(msg.operation.clone()));
+        }
+    }
+    for msg in scene_change_message.read() {
+        transcript.0.push(TranscriptEntry::Scene(msg.scene_id.clone()));
+    }
+    for msg in act_change_message.read() {
+        transcript.0.push(TranscriptEntry::Act(msg.act_id.clone()));
+    }
+}
+fn record_dialogue(
+    mut transcript: ResMut<Transcript>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut character_say_message: MessageReader<CharacterSayMessage>,
+) {
+    for msg in character_say_message.read() {
+        transcript.0.push(TranscriptEntry::Say { name: msg.name.clone(), message: msg.message.clone() });
+
+        // Advance as if the player clicked straight away
+        game_state.blocking = false;
+    }
+}
+fn answer_choices(
+    mut transcript: ResMut<Transcript>,
+    mut choices: ResMut<ScriptedChoices>,
+    mut error: ResMut<HeadlessError>,
+    mut choice_message: MessageReader<ChoiceMessage>,
+    mut choice_made_message: MessageWriter<ChoiceMadeMessage>,
+) {
+    for msg in choice_message.read() {
+        let Some(chosen) = choices.0.pop_front() else {
+            error.0 = Some(format!("Ran out of scripted choices at {:?} with options {:?}", msg.prompt, msg.options));
+            return;
+        };
+        if chosen >= msg.options.len() {
+            error.0 = Some(format!("Scripted choice {} is out of range for options {:?}", chosen, msg.options));
+            return;
+        }
+
+        transcript.0.push(TranscriptEntry::Choice { prompt: msg.prompt.clone(), options: msg.options.clone(), chosen });
+        choice_made_message.write(ChoiceMadeMessage { index: chosen });
+    }
+}
@@ -0,0 +1,7 @@
+pub mod controller;
+pub mod runner;
+
+pub use controller::HeadlessController;
+pub use controller::Transcript;
+pub use controller::TranscriptEntry;
+pub use runner::HeadlessRunner;
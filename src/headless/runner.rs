@@ -0,0 +1,110 @@
+use crate::compiler::controller::{CompilerConfig, SabiState};
+use crate::compiler::reload::{acts_source, ACTS_SOURCE};
+use crate::compiler::Compiler;
+use crate::headless::controller::{HeadlessController, HeadlessError, ScriptedChoices, Transcript};
+use crate::VisualNovelState;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use bevy::asset::AssetPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+
+// Runs acts to completion without a window, for testing stories in CI
+pub struct HeadlessRunner {
+    config: CompilerConfig,
+    choices: Vec<usize>,
+    max_frames: usize,
+}
+
+impl HeadlessRunner {
+    pub fn new(acts_path: impl Into<PathBuf>) -> Self {
+        Self {
+            config: CompilerConfig {
+                acts_path: acts_path.into(),
+                entrypoint: None,
+            },
+            choices: Vec::new(),
+            max_frames: 10_000,
+        }
+    }
+
+    pub fn with_act(self, act_id: &str) -> Self {
+        Self {
+            config: CompilerConfig {
+                entrypoint: Some(act_id.to_owned()),
+                ..self.config
+            },
+            ..self
+        }
+    }
+
+    // Option indices to pick, in the order the choices come up
+    pub fn with_choices(self, choices: Vec<usize>) -> Self {
+        Self {
+            choices,
+            ..self
+        }
+    }
+
+    // Caps both waiting for the story to start and running it
+    pub fn with_max_frames(self, max_frames: usize) -> Self {
+        Self {
+            max_frames,
+            ..self
+        }
+    }
+
+    // The app `run` steps through, once the story is about to start, for tests
+    //  that stop the story partway
+    pub fn app(self) -> Result<App> {
+        let mut app = App::new();
+        app.register_asset_source(ACTS_SOURCE, acts_source(&self.config.acts_path))
+            .add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin {
+                watch_for_changes_override: Some(false),
+                ..default()
+            }))
+            .init_resource::<VisualNovelState>()
+            .insert_resource(self.config)
+            .insert_resource(ScriptedChoices(self.choices.into()))
+            .add_plugins((
+                Compiler,
+                HeadlessController,
+            ));
+        app.finish();
+        app.cleanup();
+
+        // Scripts load on another thread, so each frame waits a little for them
+        for _ in 0..self.max_frames {
+            if *app.world().resource::<State<SabiState>>().get() == SabiState::Running {
+                return Ok(app);
+            }
+            app.update();
+            if let Some(error) = &app.world().resource::<HeadlessError>().0 {
+                bail!("{}", error);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        bail!("Story did not start within {} frames, so a controller never became ready", self.max_frames)
+    }
+
+    pub fn run(self) -> Result<Transcript> {
+        let max_frames = self.max_frames;
+        let mut app = self.app()?;
+        for _ in 0..max_frames {
+            app.update();
+
+            if let Some(error) = &app.world().resource::<HeadlessError>().0 {
+                bail!("{}", error);
+            }
+            if app.world().resource::<VisualNovelState>().is_finished() {
+                return Ok(app.world_mut().remove_resource::<Transcript>().unwrap_or_default());
+            }
+        }
+
+        bail!("Story did not finish within {} frames", max_frames)
+    }
+}
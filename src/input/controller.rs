@@ -0,0 +1,164 @@
+use crate::settings::read_settings;
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use bevy::input::mouse::MouseWheel;
+use bevy::input::InputSystems;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/* Custom types */
+// Everything the player can do, whatever it's bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Advance,
+    Skip,
+    Auto,
+    HideUi,
+    Backlog,
+    Quicksave,
+    Quickload,
+    Menu,
+    Fullscreen,
+    // Moving through and picking from a choice menu
+    MenuUp,
+    MenuDown,
+    MenuConfirm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WheelDirection {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Wheel(WheelDirection),
+    Gamepad(GamepadButton),
+}
+
+/* Components */
+// While one exists no action triggers, so typing into it can't also play the story
+#[derive(Component)]
+pub struct CapturesInput;
+
+/* Resources */
+// Which bindings trigger each action; `settings/input.json` overrides them per action
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "HashMap<Action, Vec<Binding>>")]
+pub struct InputMap(pub HashMap<Action, Vec<Binding>>);
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::*;
+        Self(HashMap::from([
+            (Action::Advance, vec![Mouse(MouseButton::Left), Key(KeyCode::Enter), Key(KeyCode::Space), Wheel(WheelDirection::Down), Gamepad(GamepadButton::South)]),
+            (Action::Skip, vec![Key(KeyCode::KeyS), Gamepad(GamepadButton::RightTrigger)]),
+            (Action::Auto, vec![Key(KeyCode::KeyA), Gamepad(GamepadButton::West)]),
+            (Action::HideUi, vec![Key(KeyCode::KeyV), Mouse(MouseButton::Middle), Gamepad(GamepadButton::North)]),
+            (Action::Backlog, vec![Key(KeyCode::KeyH), Wheel(WheelDirection::Up), Gamepad(GamepadButton::LeftTrigger)]),
+            (Action::Quicksave, vec![Key(KeyCode::F5)]),
+            (Action::Quickload, vec![Key(KeyCode::F9)]),
+            (Action::Menu, vec![Key(KeyCode::Escape), Mouse(MouseButton::Right), Gamepad(GamepadButton::Start)]),
+            (Action::Fullscreen, vec![Key(KeyCode::F11)]),
+            (Action::MenuUp, vec![Key(KeyCode::ArrowUp), Gamepad(GamepadButton::DPadUp)]),
+            (Action::MenuDown, vec![Key(KeyCode::ArrowDown), Gamepad(GamepadButton::DPadDown)]),
+            (Action::MenuConfirm, vec![Key(KeyCode::Enter), Key(KeyCode::Space), Gamepad(GamepadButton::South)]),
+        ]))
+    }
+}
+// Actions missing from the file keep their default bindings
+impl From<HashMap<Action, Vec<Binding>>> for InputMap {
+    fn from(overrides: HashMap<Action, Vec<Binding>>) -> Self {
+        let mut input_map = Self::default();
+        input_map.0.extend(overrides);
+        input_map
+    }
+}
+
+// Actions triggered this frame
+#[derive(Resource, Debug, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    clicked: HashSet<Action>,
+    scrolled: HashSet<Action>,
+}
+impl ActionState {
+    // Triggered by anything, the wheel included
+    pub fn triggered(&self, action: Action) -> bool {
+        self.pressed.contains(&action) || self.clicked.contains(&action) || self.scrolled.contains(&action)
+    }
+
+    // Triggered by a key or button, for actions the wheel shouldn't undo
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action) || self.clicked.contains(&action)
+    }
+
+    // Triggered by a mouse button, for actions that only count over part of the screen
+    pub fn clicked(&self, action: Action) -> bool {
+        self.clicked.contains(&action)
+    }
+
+    // Triggered by anything but a mouse button
+    pub fn triggered_without_clicking(&self, action: Action) -> bool {
+        self.pressed.contains(&action) || self.scrolled.contains(&action)
+    }
+}
+
+pub struct InputController;
+impl Plugin for InputController {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<ActionState>()
+            .add_systems(Startup, load_input_map)
+            .add_systems(PreUpdate, read_actions.after(InputSystems));
+    }
+}
+
+pub fn input_map_path() -> PathBuf {
+    PathBuf::from(".").join("settings").join("input.json")
+}
+fn load_input_map(mut input_map: ResMut<InputMap>) -> Result<(), BevyError> {
+    *input_map = read_settings(&input_map_path())?;
+    Ok(())
+}
+fn read_actions(
+    input_map: Res<InputMap>,
+    mut action_state: ResMut<ActionState>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut wheel_messages: MessageReader<MouseWheel>,
+    gamepads: Query<&Gamepad>,
+    capture_query: Query<(), With<CapturesInput>>,
+) {
+    action_state.pressed.clear();
+    action_state.clicked.clear();
+    action_state.scrolled.clear();
+    if !capture_query.is_empty() {
+        wheel_messages.clear();
+        return;
+    }
+
+    let mut wheel_up = false;
+    let mut wheel_down = false;
+    for wheel in wheel_messages.read() {
+        wheel_up |= wheel.y > 0.;
+        wheel_down |= wheel.y < 0.;
+    }
+
+    for (action, bindings) in &input_map.0 {
+        for binding in bindings {
+            match binding {
+                Binding::Key(key) if keys.just_pressed(*key) => action_state.pressed.insert(*action),
+                Binding::Mouse(button) if buttons.just_pressed(*button) => action_state.clicked.insert(*action),
+                Binding::Gamepad(button) if gamepads.iter().any(|gamepad| gamepad.just_pressed(*button)) => action_state.pressed.insert(*action),
+                Binding::Wheel(WheelDirection::Up) if wheel_up => action_state.scrolled.insert(*action),
+                Binding::Wheel(WheelDirection::Down) if wheel_down => action_state.scrolled.insert(*action),
+                _ => false,
+            };
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod controller;
+
+pub use controller::Action;
+pub use controller::ActionState;
+pub use controller::Binding;
+pub use controller::CapturesInput;
+pub use controller::InputController;
+pub use controller::InputMap;
+pub use controller::WheelDirection;
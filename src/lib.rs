@@ -0,0 +1,96 @@
+pub mod audio;
+pub mod background;
+pub mod character;
+pub mod chat;
+pub mod compiler;
+pub mod display;
+pub mod headless;
+pub mod input;
+pub mod player;
+pub mod save;
+pub mod settings;
+pub mod tween;
+
+use crate::audio::*;
+use crate::background::*;
+use crate::character::*;
+use crate::chat::*;
+use crate::compiler::ast;
+use crate::compiler::diagnostics::Span;
+use crate::compiler::execution::ExecutionStack;
+use crate::save::persistent::PersistentData;
+
+use bevy::asset::AssetLoader;
+use bevy::{
+    prelude::*,
+    asset::Handle,
+};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CharacterJsonError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON parse error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+#[derive(Default)]
+pub struct CharacterJsonLoader;
+impl AssetLoader for CharacterJsonLoader {
+    type Asset = CharacterConfig;
+    type Settings = ();
+    type Error = CharacterJsonError;
+
+    fn load(
+            &self,
+            reader: &mut dyn bevy::asset::io::Reader,
+            _settings: &Self::Settings,
+            _load_context: &mut bevy::asset::LoadContext,
+        ) -> impl bevy::tasks::ConditionalSendFuture<Output = std::result::Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let parsed: CharacterConfig = serde_json::from_slice(&bytes)?;
+            Ok(parsed)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
+#[derive(Component)]
+pub struct Object {
+    id: String
+}
+impl Object {
+    pub fn new(id: &str) -> Self {
+        Self { id: id.to_owned() }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct VisualNovelState {
+    // Assets
+    gui_sprites: HashMap<String, Handle<Image>>,
+
+    // Game state
+    acts: ast::Acts,
+    act_id: String,
+    act: Box<ast::Act>,
+    scene_id: String,
+    scene: Box<ast::Scene>,
+    statements: ExecutionStack,
+    // The statement that ran last, which errors from its messages point at
+    current_span: Option<Span>,
+    variables: ast::Variables,
+    blocking: bool,
+    // Lines already shown, for the history overlay
+    history: History,
+    // Whether the line on screen was seen in any playthrough
+    line_read: bool,
+    persistent: PersistentData,
+}
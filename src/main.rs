@@ -1,91 +1,37 @@
-mod background;
-mod character;
-mod chat;
-mod compiler;
+use sabi::audio::AudioController;
+use sabi::background::BackgroundController;
+use sabi::character::{CharacterConfig, CharacterController};
+use sabi::chat::{ChatController, ChoiceController, HistoryController, PlaybackController};
+use sabi::compiler::Compiler;
+use sabi::compiler::controller::CompilerConfig;
+use sabi::compiler::reload::{acts_source, ActReloader, ACTS_SOURCE};
+use sabi::display::{DisplayController, DESIGN_RESOLUTION};
+use sabi::input::InputController;
+use sabi::player::PlayerController;
+use sabi::save::SaveController;
+use sabi::tween::TweenController;
+use sabi::{CharacterJsonLoader, VisualNovelState};
 
-use crate::background::*;
-use crate::character::*;
-use crate::chat::*;
-use crate::compiler::*;
-use crate::compiler::ast;
-
-use bevy::asset::AssetLoader;
 use bevy::ecs::error::ErrorContext;
 use bevy::{
     prelude::*,
     window::*,
-    asset::Handle,
 };
-use std::vec::IntoIter;
-use std::collections::HashMap;
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum CharacterJsonError {
-    #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("JSON parse error: {0}")]
-    Serde(#[from] serde_json::Error),
-}
-
-#[derive(Default)]
-pub struct CharacterJsonLoader;
-impl AssetLoader for CharacterJsonLoader {
-    type Asset = CharacterConfig;
-    type Settings = ();
-    type Error = CharacterJsonError;
-
-    fn load(
-            &self,
-            reader: &mut dyn bevy::asset::io::Reader,
-            _settings: &Self::Settings,
-            _load_context: &mut bevy::asset::LoadContext,
-        ) -> impl bevy::tasks::ConditionalSendFuture<Output = std::result::Result<Self::Asset, Self::Error>> {
-        Box::pin(async move {
-            let mut bytes = Vec::new();
-            reader.read_to_end(&mut bytes).await?;
-            let parsed: CharacterConfig = serde_json::from_slice(&bytes)?;
-            Ok(parsed)
-        })
-    }
-
-    fn extensions(&self) -> &[&str] {
-        &["json"]
-    }
-}
-
-#[derive(Component)]
-struct Object {
-    id: String
-}
-
-#[derive(Resource, Default)]
-pub struct VisualNovelState {
-    // Player-designated constants
-    playername: String,
-
-    // Assets
-    gui_sprites: HashMap<String, Handle<Image>>,
-
-    // Game state
-    acts: ast::Acts,
-    act: Box<ast::Act>,
-    scene: Box<ast::Scene>,
-    statements: IntoIter<ast::Statement>,
-    blocking: bool,
-}
 
 fn error_handler ( err: BevyError, ctx: ErrorContext ) {
     panic!("Bevy error: {err:?}\nContext: {ctx:?}")
 }
 
 fn main() {
+    let config = CompilerConfig::default();
     App::new()
+        .register_asset_source(ACTS_SOURCE, acts_source(&config.acts_path))
+        .insert_resource(config)
         .add_plugins(DefaultPlugins
             .set(WindowPlugin {
                 primary_window: Some(Window {
                     title: String::from("Sabi"),
-                    resolution: (1280, 800).into(),
+                    resolution: DESIGN_RESOLUTION.as_uvec2().into(),
                     present_mode: PresentMode::AutoVsync,
                     prevent_default_event_handling: false,
                     ..default()
@@ -99,21 +45,25 @@ fn main() {
         .set_error_handler(error_handler)
         .add_systems(Startup, setup)
         .add_plugins((
+            InputController,
+            DisplayController,
             Compiler,
+            ActReloader,
+            AudioController,
             BackgroundController,
             CharacterController,
             ChatController,
+            ChoiceController,
+            HistoryController,
+            PlaybackController,
+            PlayerController,
+            SaveController,
+            TweenController,
         ))
         .run();
 }
 
-fn setup(
-    mut commands: Commands,
-    mut game_state: ResMut<VisualNovelState>,
-) {
-    // This would normally be filled in by the player
-    game_state.playername = String::from("Bolt");
-
+fn setup(mut commands: Commands) {
     // Create our primary camera (which is
     //  necessary even for 2D games)
     commands.spawn(Camera2d::default());
@@ -0,0 +1,244 @@
+use crate::compiler::controller::SabiState;
+use crate::display::Letterbox;
+use crate::input::CapturesInput;
+use crate::VisualNovelState;
+
+use anyhow::{ensure, Result};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use bevy::window::{Ime, PrimaryWindow};
+use unicode_segmentation::UnicodeSegmentation;
+
+// What MC is called until the player picks a name, like in headless runs
+pub const DEFAULT_PLAYER_NAME: &str = "Player";
+// In graphemes, so it fits the namebox whatever the script
+pub const PLAYER_NAME_LIMIT: usize = 16;
+
+const BUTTON_COLOR: Color = Color::srgba(0.35, 0.22, 0.45, 0.95);
+const ERROR_COLOR: Color = Color::srgb(1., 0.55, 0.55);
+
+/* Components */
+// The name being typed on the first launch screen
+#[derive(Component, Default)]
+pub struct NameEntry {
+    pub name: String,
+    // Text the IME is still composing, shown but not yet part of the name
+    pub preedit: String,
+    pub error: Option<String>,
+    submitted: bool,
+}
+#[derive(Component)]
+struct NameField;
+#[derive(Component)]
+struct NameError;
+#[derive(Component)]
+struct NameConfirmButton;
+
+pub struct PlayerController;
+impl Plugin for PlayerController {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(SabiState::Running), open_name_entry)
+            .add_systems(Update, (type_name, click_confirm, confirm_name, show_name_entry).chain().run_if(in_state(SabiState::Running)));
+    }
+}
+
+// The name as it'll be stored, or why it can't be. Braces would read as markup in dialogue
+pub fn validate_player_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    ensure!(!name.is_empty(), "Please enter a name");
+    ensure!(name.graphemes(true).count() <= PLAYER_NAME_LIMIT,
+        "Names can be at most {} characters long", PLAYER_NAME_LIMIT);
+    ensure!(!name.chars().any(char::is_control), "Names can't contain control characters");
+    ensure!(!name.contains(['{', '}']), "Names can't contain '{{' or '}}'");
+    Ok(name.to_owned())
+}
+
+// Asks for a name before the story starts, unless an earlier launch already did
+fn open_name_entry(
+    mut commands: Commands,
+    mut game_state: ResMut<VisualNovelState>,
+    asset_server: Res<AssetServer>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if game_state.persistent.player_name.is_some() {
+        return;
+    }
+    game_state.blocking = true;
+    if let Ok(mut window) = window.single_mut() {
+        window.ime_enabled = true;
+    }
+
+    let font = |path: &str, font_size: f32| TextFont {
+        font: asset_server.load(path.to_owned()),
+        font_size,
+        ..default()
+    };
+    commands.spawn((
+        NameEntry::default(),
+        CapturesInput,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(16.),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.03, 0.03, 0.05, 0.95)),
+        FocusPolicy::Block,
+        GlobalZIndex(60),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::new("What's your name?"),
+            font("fonts/BOLD.ttf", 32.),
+            TextColor::WHITE,
+        ));
+        parent.spawn((
+            Node {
+                width: Val::Px(420.),
+                padding: UiRect::axes(Val::Px(12.), Val::Px(8.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            BorderColor::all(BUTTON_COLOR),
+            BackgroundColor(Color::srgba(0.08, 0.08, 0.1, 0.9)),
+        ))
+        .with_children(|field| {
+            field.spawn((
+                NameField,
+                Text::new(""),
+                font("fonts/ALLER.ttf", 28.),
+                TextColor::WHITE,
+            ));
+        });
+        parent.spawn((
+            NameError,
+            Text::new(""),
+            font("fonts/ALLER.ttf", 18.),
+            TextColor(ERROR_COLOR),
+        ));
+        parent.spawn((
+            Button,
+            NameConfirmButton,
+            Node {
+                width: Val::Px(120.),
+                padding: UiRect::all(Val::Px(6.)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+        ))
+        .with_children(|button| {
+            button.spawn((
+                Text::new("OK"),
+                font("fonts/ALLER.ttf", 22.),
+                TextColor::WHITE,
+            ));
+        });
+    });
+}
+// Keys the IME isn't composing with arrive as keyboard text, everything else as commits
+fn type_name(
+    mut keyboard_messages: MessageReader<KeyboardInput>,
+    mut ime_messages: MessageReader<Ime>,
+    mut entry_query: Query<&mut NameEntry>,
+) {
+    let Ok(mut entry) = entry_query.single_mut() else {
+        keyboard_messages.clear();
+        ime_messages.clear();
+        return;
+    };
+
+    for ime in ime_messages.read() {
+        match ime {
+            Ime::Preedit { value, .. } => entry.preedit = value.clone(),
+            Ime::Commit { value, .. } => {
+                entry.preedit.clear();
+                entry.name.push_str(value);
+            },
+            _ => {},
+        }
+    }
+    for input in keyboard_messages.read() {
+        // Enter and Backspace belong to the IME while it's composing
+        if !input.state.is_pressed() || !entry.preedit.is_empty() {
+            continue;
+        }
+        match (&input.logical_key, &input.text) {
+            (Key::Enter, _) => entry.submitted = true,
+            (Key::Backspace, _) => {
+                let end = entry.name.grapheme_indices(true).next_back().map_or(0, |(index, _)| index);
+                entry.name.truncate(end);
+            },
+            (_, Some(text)) if !text.chars().any(char::is_control) => entry.name.push_str(text),
+            _ => {},
+        }
+    }
+}
+fn click_confirm(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<NameConfirmButton>)>,
+    mut entry_query: Query<&mut NameEntry>,
+) {
+    if interaction_query.iter().any(|interaction| *interaction == Interaction::Pressed)
+        && let Ok(mut entry) = entry_query.single_mut() {
+        entry.submitted = true;
+    }
+}
+fn confirm_name(
+    mut commands: Commands,
+    mut game_state: ResMut<VisualNovelState>,
+    mut entry_query: Query<(Entity, &mut NameEntry)>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok((entity, mut entry)) = entry_query.single_mut() else {
+        return;
+    };
+    if !entry.submitted {
+        return;
+    }
+    entry.submitted = false;
+
+    match validate_player_name(&entry.name) {
+        Ok(name) => {
+            info!("[ Player name set to '{}' ]", name);
+            game_state.persistent.set_player_name(name);
+            game_state.blocking = false;
+            commands.entity(entity).despawn();
+            if let Ok(mut window) = window.single_mut() {
+                window.ime_enabled = false;
+            }
+        },
+        Err(error) => entry.error = Some(error.to_string()),
+    }
+}
+// Keeps the field's `Text` apart from the error's
+type FieldFilter = (With<NameField>, Without<NameError>);
+
+fn show_name_entry(
+    entry_query: Query<&NameEntry, Changed<NameEntry>>,
+    mut field_query: Query<(&mut Text, &ComputedNode, &UiGlobalTransform), FieldFilter>,
+    mut error_query: Query<&mut Text, (With<NameError>, Without<NameField>)>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(entry) = entry_query.single() else {
+        return;
+    };
+    for (mut text, node, transform) in field_query.iter_mut() {
+        text.0 = format!("{}{}_", entry.name, entry.preedit);
+
+        // The IME's candidate list opens under the field; UI positions are
+        //  physical pixels inside the letterboxed view
+        if let Ok(mut window) = window.single_mut() {
+            let letterbox = Letterbox::fit(window.physical_size().as_vec2());
+            let below = letterbox.position + transform.translation + Vec2::new(-node.size().x, node.size().y) / 2.;
+            window.ime_position = below / window.scale_factor();
+        }
+    }
+    for mut text in error_query.iter_mut() {
+        text.0 = entry.error.clone().unwrap_or_default();
+    }
+}
@@ -0,0 +1,7 @@
+pub mod controller;
+
+pub use controller::PlayerController;
+pub use controller::NameEntry;
+pub use controller::validate_player_name;
+pub use controller::DEFAULT_PLAYER_NAME;
+pub use controller::PLAYER_NAME_LIMIT;
@@ -0,0 +1,293 @@
+use crate::character::{StagePlacement, StageSlot};
+use crate::compiler::ast::Variables;
+use crate::compiler::controller::SabiState;
+use crate::compiler::execution::ProgramCounter;
+use crate::input::{Action, ActionState};
+use crate::save::persistent::{flush_persistent, load_persistent};
+use crate::{AudioChangeMessage, AudioOperation, CurrentMusic, Background, BackgroundChangeMessage, CharacterChangeMessage, CharacterConfig, CharacterOperation, CharacterSayMessage, ChoiceMenu, ChoiceMessage, Configs, GUIChangeMessage, GUIScrollText, History, Object, VisualNovelState};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/* Messages */
+#[derive(Message)]
+pub struct SaveGameMessage {
+    pub slot: usize
+}
+#[derive(Message)]
+pub struct LoadGameMessage {
+    pub slot: usize
+}
+
+/* Custom types */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterSave {
+    pub name: String,
+    pub emotion: String,
+    pub outfit: String,
+    // Older saves let the stage lay everyone out
+    #[serde(default)]
+    pub position: Option<StageSlot>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextboxSave {
+    pub name: String,
+    pub message: String,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChoiceSave {
+    pub prompt: Option<String>,
+    pub options: Vec<String>,
+}
+
+// Everything needed to put the story back exactly where it was
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SaveData {
+    pub program_counter: ProgramCounter,
+    pub variables: Variables,
+    pub background: Option<String>,
+    pub music: Option<String>,
+    pub gui: HashMap<String, String>,
+    pub characters: Vec<CharacterSave>,
+    pub textbox: Option<TextboxSave>,
+    pub choice: Option<ChoiceSave>,
+    pub history: History,
+}
+
+// GUI elements that show an image, as opposed to the background or characters
+type GuiSpriteFilter = (Without<Background>, Without<CharacterConfig>);
+
+// Everything on screen that a save records
+#[derive(SystemParam)]
+pub struct StageQueries<'w, 's> {
+    background_query: Query<'w, 's, &'static Background>,
+    gui_query: Query<'w, 's, (&'static Object, &'static Sprite), GuiSpriteFilter>,
+    character_query: Query<'w, 's, (&'static CharacterConfig, &'static StagePlacement), With<Sprite>>,
+    text_query: Query<'w, 's, (&'static Object, &'static Text2d, &'static GUIScrollText)>,
+    visibility_query: Query<'w, 's, (&'static Object, &'static Visibility)>,
+    choice_query: Query<'w, 's, &'static ChoiceMenu>,
+}
+
+// What a load clears off the screen before putting the saved stage back
+#[derive(SystemParam)]
+pub struct StageEntities<'w, 's> {
+    character_query: Query<'w, 's, &'static CharacterConfig, With<Sprite>>,
+    choice_query: Query<'w, 's, Entity, With<ChoiceMenu>>,
+    visibility_query: Query<'w, 's, (&'static Object, &'static mut Visibility)>,
+}
+
+// The messages that put a saved stage back
+#[derive(SystemParam)]
+pub struct StageWriters<'w> {
+    background_change_message: MessageWriter<'w, BackgroundChangeMessage>,
+    audio_change_message: MessageWriter<'w, AudioChangeMessage>,
+    gui_change_message: MessageWriter<'w, GUIChangeMessage>,
+    character_change_message: MessageWriter<'w, CharacterChangeMessage>,
+    character_say_message: MessageWriter<'w, CharacterSayMessage>,
+    choice_message: MessageWriter<'w, ChoiceMessage>,
+}
+
+pub const QUICKSAVE_SLOT: usize = 1;
+
+pub struct SaveController;
+impl Plugin for SaveController {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SaveGameMessage>()
+            .add_message::<LoadGameMessage>()
+            .add_systems(Startup, load_persistent)
+            .add_systems(Update, (quicksave_keys, handle_saves, handle_loads).chain().run_if(in_state(SabiState::Running)))
+            .add_systems(Update, flush_persistent);
+    }
+}
+
+pub fn slot_path(slot: usize) -> PathBuf {
+    PathBuf::from(".").join("saves").join(format!("slot_{}.json", slot))
+}
+fn write_slot(slot: usize, save_data: &SaveData) -> Result<()> {
+    let path = slot_path(slot);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create save directory {:?}", parent))?;
+    }
+    let contents = serde_json::to_string_pretty(save_data)
+        .context("Failed to serialize save data")?;
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Failed to write save file {:?}", path))
+}
+fn read_slot(slot: usize) -> Result<SaveData> {
+    let path = slot_path(slot);
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read save file {:?}", path))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse save file {:?}", path))
+}
+
+fn quicksave_keys(
+    actions: Res<ActionState>,
+    mut save_message: MessageWriter<SaveGameMessage>,
+    mut load_message: MessageWriter<LoadGameMessage>,
+) {
+    if actions.triggered(Action::Quicksave) {
+        save_message.write(SaveGameMessage { slot: QUICKSAVE_SLOT });
+    }
+    if actions.triggered(Action::Quickload) {
+        load_message.write(LoadGameMessage { slot: QUICKSAVE_SLOT });
+    }
+}
+pub fn handle_saves(
+    mut save_messages: MessageReader<SaveGameMessage>,
+    game_state: Res<VisualNovelState>,
+    current_music: Res<CurrentMusic>,
+    stage: StageQueries,
+) -> Result<(), BevyError> {
+    let StageQueries { background_query, gui_query, character_query, text_query, visibility_query, choice_query } = stage;
+    for msg in save_messages.read() {
+        let background = background_query.iter()
+            .find_map(|background| background.current.clone());
+
+        // GUI elements only know their image, so map it back to its sprite id
+        let gui = gui_query.iter()
+            .filter_map(|(gui_obj, sprite)| {
+                game_state.gui_sprites.iter()
+                    .find(|(_, handle)| **handle == sprite.image)
+                    .map(|(sprite_id, _)| (gui_obj.id.clone(), sprite_id.clone()))
+            })
+            .collect();
+
+        // In the order they came on stage, so loading keeps who stands in front
+        let mut cast: Vec<(&CharacterConfig, &StagePlacement)> = character_query.iter().collect();
+        cast.sort_by_key(|(_, placement)| placement.order);
+        let characters = cast.into_iter()
+            .map(|(config, placement)| CharacterSave {
+                name: config.name.clone(),
+                emotion: config.emotion.clone(),
+                outfit: config.outfit.clone(),
+                position: placement.slot,
+            })
+            .collect();
+
+        let textbox_visible = visibility_query.iter()
+            .any(|(obj, visibility)| obj.id == "_textbox_background" && *visibility != Visibility::Hidden);
+        let textbox = if textbox_visible {
+            let name = text_query.iter()
+                .find(|(obj, _, _)| obj.id == "_name_text")
+                .map(|(_, text, _)| text.0.clone())
+                .context("Missing GUI text object with ID '_name_text'")?;
+            let message = text_query.iter()
+                .find(|(obj, _, _)| obj.id == "_message_text")
+                .map(|(_, _, scroll_text)| scroll_text.message.clone())
+                .context("Missing GUI scroll text object with ID '_message_text'")?;
+            Some(TextboxSave { name, message })
+        } else { None };
+
+        let choice = choice_query.iter()
+            .next()
+            .map(|menu| ChoiceSave {
+                prompt: menu.prompt.clone(),
+                options: menu.options.clone(),
+            });
+
+        let save_data = SaveData {
+            program_counter: game_state.program_counter(),
+            variables: game_state.variables.clone(),
+            background,
+            music: current_music.0.clone(),
+            gui,
+            characters,
+            textbox,
+            choice,
+            history: game_state.history.clone(),
+        };
+        write_slot(msg.slot, &save_data)
+            .with_context(|| format!("Failed to save to slot {}", msg.slot))?;
+        info!("[ Saved to slot {} ]", msg.slot);
+    }
+    Ok(())
+}
+pub fn handle_loads(
+    mut commands: Commands,
+    mut load_messages: MessageReader<LoadGameMessage>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut configs: ResMut<Configs>,
+    stage: StageEntities,
+    writers: StageWriters,
+) -> Result<(), BevyError> {
+    let StageEntities { character_query, choice_query, mut visibility_query } = stage;
+    let StageWriters {
+        mut background_change_message,
+        mut audio_change_message,
+        mut gui_change_message,
+        mut character_change_message,
+        mut character_say_message,
+        mut choice_message,
+    } = writers;
+    for msg in load_messages.read() {
+        let save_data = read_slot(msg.slot)
+            .with_context(|| format!("Failed to load slot {}", msg.slot))?;
+
+        game_state.restore_program_counter(&save_data.program_counter)
+            .with_context(|| format!("Failed to restore slot {}", msg.slot))?;
+        game_state.variables = save_data.variables;
+        game_state.history = save_data.history;
+
+        if let Some(background_id) = save_data.background {
+            background_change_message.write(BackgroundChangeMessage { background_id, transition: None });
+        }
+        let operation = match save_data.music {
+            Some(music_id) => AudioOperation::PlayMusic(music_id, 0.),
+            None => AudioOperation::StopMusic(0.),
+        };
+        audio_change_message.write(AudioChangeMessage { operation });
+        for (gui_id, sprite_id) in save_data.gui {
+            gui_change_message.write(GUIChangeMessage { gui_id, sprite_id });
+        }
+
+        // Replace whoever is on stage with the saved cast
+        for config in character_query.iter() {
+            character_change_message.write(CharacterChangeMessage {
+                character: config.name.clone(),
+                operation: CharacterOperation::Despawn(0.),
+            });
+        }
+        for character in save_data.characters {
+            let config = configs.0.get_mut(&character.name)
+                .with_context(|| format!("Character config not found for {}", character.name))?;
+            config.outfit = character.outfit;
+            character_change_message.write(CharacterChangeMessage {
+                character: character.name,
+                operation: CharacterOperation::Spawn(Some(character.emotion), character.position, 0.),
+            });
+        }
+
+        // Put back whatever the player was looking at
+        for entity in choice_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        for (obj, mut visibility) in visibility_query.iter_mut() {
+            if obj.id == "_textbox_background" {
+                *visibility = Visibility::Hidden;
+            }
+        }
+        game_state.blocking = save_data.textbox.is_some() || save_data.choice.is_some();
+        if let Some(textbox) = save_data.textbox {
+            character_say_message.write(CharacterSayMessage {
+                name: textbox.name,
+                message: textbox.message,
+            });
+        }
+        if let Some(choice) = save_data.choice {
+            choice_message.write(ChoiceMessage {
+                prompt: choice.prompt,
+                options: choice.options,
+            });
+        }
+
+        info!("[ Loaded slot {} at act '{}', scene '{}' ]", msg.slot, save_data.program_counter.act, save_data.program_counter.scene);
+    }
+    Ok(())
+}
@@ -0,0 +1,5 @@
+pub mod controller;
+pub mod persistent;
+
+pub use controller::SaveController;
+pub use persistent::PersistentData;
\ No newline at end of file
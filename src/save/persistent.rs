@@ -0,0 +1,188 @@
+use crate::compiler::ast::{Dialogue, Expr, Scope, Variables};
+use crate::compiler::execution::ProgramCounter;
+use crate::player::{validate_player_name, DEFAULT_PLAYER_NAME};
+use crate::settings::read_settings;
+use crate::VisualNovelState;
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/* Custom types */
+// Progress shared by every playthrough and save slot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersistentData {
+    #[serde(default)]
+    pub seen_lines: BTreeSet<String>,
+    #[serde(default)]
+    pub gallery: BTreeSet<String>,
+    #[serde(default)]
+    pub endings: BTreeSet<String>,
+    #[serde(default)]
+    pub flags: Variables,
+    // Asked for on the first launch
+    #[serde(default)]
+    pub player_name: Option<String>,
+    // Skip mode normally stops at the first line the player hasn't seen
+    #[serde(default)]
+    pub skip_unread: bool,
+    // Changed since it was last written to disk
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl PersistentData {
+    // Records a line as seen, returning whether this is the first time
+    pub fn mark_seen(&mut self, line_id: String) -> bool {
+        let first_time = self.seen_lines.insert(line_id);
+        self.dirty |= first_time;
+        first_time
+    }
+
+    pub fn has_seen(&self, line_id: &str) -> bool {
+        self.seen_lines.contains(line_id)
+    }
+
+    pub fn set_flag(&mut self, name: &str, value: Expr) -> Result<()> {
+        self.flags.set(name, value)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn set_player_name(&mut self, name: String) {
+        self.dirty |= self.player_name.as_ref() != Some(&name);
+        self.player_name = Some(name);
+    }
+
+    pub fn set_skip_unread(&mut self, skip_unread: bool) {
+        self.dirty |= self.skip_unread != skip_unread;
+        self.skip_unread = skip_unread;
+    }
+
+    pub fn set_unlocked(&mut self, item: &str, unlocked: bool) {
+        self.dirty |= set_membership(&mut self.gallery, item, unlocked);
+    }
+
+    pub fn set_cleared(&mut self, ending: &str, cleared: bool) {
+        self.dirty |= set_membership(&mut self.endings, ending, cleared);
+    }
+}
+fn set_membership(set: &mut BTreeSet<String>, item: &str, member: bool) -> bool {
+    if member {
+        set.insert(item.to_owned())
+    } else {
+        set.remove(item)
+    }
+}
+
+// Identifies a line by its place in the scene and what it says, so the same
+//  line said twice is told apart but blank lines and edits to other blocks
+//  keep it the same; FNV-1a keeps the hash stable across builds.
+//  `program_counter` is taken while the line runs, so it points just past it
+pub fn line_id(program_counter: &ProgramCounter, dialogue: &Dialogue) -> String {
+    let content = serde_json::to_string(&dialogue.dialogue).unwrap_or_default();
+    let position = program_counter.frames.iter()
+        .map(|frame| format!("{}.{}", frame.branch, frame.index))
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in position.bytes().chain([0]).chain(dialogue.character.bytes()).chain([0]).chain(content.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{}/{}/{:016x}", program_counter.act, program_counter.scene, hash)
+}
+
+// Names with a namespace reach the persistent data instead of the save's variables:
+//  `global.x` is any flag, `gallery.x` and `ending.x` are booleans, `player.name` a string
+impl Scope for VisualNovelState {
+    fn lookup(&self, name: &str) -> Option<Expr> {
+        match name.split_once('.') {
+            // Flags that were never set read as false, as on a first playthrough
+            Some(("global", flag)) => Some(self.persistent.flags.get(flag).cloned().unwrap_or(Expr::Boolean(false))),
+            Some(("gallery", item)) => Some(Expr::Boolean(self.persistent.gallery.contains(item))),
+            Some(("ending", ending)) => Some(Expr::Boolean(self.persistent.endings.contains(ending))),
+            Some(("player", "name")) => Some(Expr::String(self.player_name().to_owned())),
+            Some(("player", _)) => None,
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+}
+impl VisualNovelState {
+    pub fn set_variable(&mut self, name: &str, value: Expr) -> Result<()> {
+        match name.split_once('.') {
+            Some(("global", flag)) => self.persistent.set_flag(flag, value),
+            Some(("gallery", item)) => {
+                self.persistent.set_unlocked(item, expect_bool(name, &value)?);
+                Ok(())
+            },
+            Some(("ending", ending)) => {
+                self.persistent.set_cleared(ending, expect_bool(name, &value)?);
+                Ok(())
+            },
+            Some(("player", "name")) => {
+                let Expr::String(player_name) = &value else {
+                    bail!("'{}' can only be set to a string, not a {}", name, value.type_name());
+                };
+                self.persistent.set_player_name(validate_player_name(player_name)?);
+                Ok(())
+            },
+            Some(("player", _)) => bail!("'{}' is not something the player has", name),
+            _ => self.variables.set(name, value),
+        }
+    }
+
+    pub fn persistent(&self) -> &PersistentData {
+        &self.persistent
+    }
+
+    pub fn player_name(&self) -> &str {
+        self.persistent.player_name.as_deref().unwrap_or(DEFAULT_PLAYER_NAME)
+    }
+
+    // What the namebox shows for a speaker; MC is whoever's playing
+    pub fn speaker_name(&self, character: &str) -> String {
+        match character {
+            "MC" => self.player_name().to_owned(),
+            _ => character.to_owned(),
+        }
+    }
+}
+fn expect_bool(name: &str, value: &Expr) -> Result<bool> {
+    match value {
+        Expr::Boolean(b) => Ok(*b),
+        other => bail!("'{}' can only be set to a boolean, not a {}", name, other.type_name()),
+    }
+}
+
+pub fn persistent_path() -> PathBuf {
+    PathBuf::from(".").join("saves").join("persistent.json")
+}
+pub fn write_persistent(path: &Path, data: &PersistentData) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create save directory {:?}", parent))?;
+    }
+    let contents = serde_json::to_string_pretty(data)
+        .context("Failed to serialize persistent data")?;
+    std::fs::write(path, contents)
+        .with_context(|| format!("Failed to write persistent data {:?}", path))
+}
+
+// A missing file is a first playthrough
+pub fn load_persistent(mut game_state: ResMut<VisualNovelState>) -> Result<(), BevyError> {
+    game_state.persistent = read_settings(&persistent_path())?;
+    Ok(())
+}
+// Written as soon as anything changes, so quitting never loses progress
+pub fn flush_persistent(mut game_state: ResMut<VisualNovelState>) -> Result<(), BevyError> {
+    if !game_state.persistent.dirty {
+        return Ok(());
+    }
+    write_persistent(&persistent_path(), &game_state.persistent)?;
+    game_state.persistent.dirty = false;
+    Ok(())
+}
@@ -0,0 +1,3 @@
+pub mod reader;
+
+pub use reader::read_settings;
@@ -0,0 +1,16 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+
+// A missing file is all defaults, and anything missing from the file keeps its default
+//  as far as the type's own `Deserialize` allows
+pub fn read_settings<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {:?}", path))
+}
@@ -0,0 +1,235 @@
+use crate::VisualNovelState;
+
+use bevy::color::Mix;
+use bevy::ecs::query::QueryData;
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+
+/* Custom types */
+// Eased progress for `t` from 0 to 1, starting and stopping gently
+pub fn ease(t: f32) -> f32 {
+    let t = t.clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+// What a tween animates towards. Alpha and tint only touch the entity's color, be it
+//  a sprite's, text's or a node's background, and leave each other alone, so a tinted
+//  sprite can still fade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweenTarget {
+    Alpha(f32),
+    Tint(Color),
+    Translation(Vec3),
+    Progress(f32),
+}
+impl TweenTarget {
+    // The same property's current value on an entity
+    fn current(&self, properties: &Properties) -> Option<TweenTarget> {
+        Some(match self {
+            TweenTarget::Alpha(_) => TweenTarget::Alpha(properties.color.as_deref()?.alpha()),
+            TweenTarget::Tint(_) => TweenTarget::Tint(properties.color.as_deref()?.with_alpha(1.)),
+            TweenTarget::Translation(_) => TweenTarget::Translation(properties.transform.as_deref()?.translation),
+            TweenTarget::Progress(_) => TweenTarget::Progress(properties.progress.as_deref()?.0),
+        })
+    }
+
+    // `t` of the way from `self` to `to`, which is the same property
+    pub fn lerp(&self, to: &TweenTarget, t: f32) -> TweenTarget {
+        match (self, to) {
+            (TweenTarget::Alpha(from), TweenTarget::Alpha(to)) => TweenTarget::Alpha(from + (to - from) * t),
+            (TweenTarget::Tint(from), TweenTarget::Tint(to)) => TweenTarget::Tint(from.mix(to, t)),
+            (TweenTarget::Translation(from), TweenTarget::Translation(to)) => TweenTarget::Translation(from.lerp(*to, t)),
+            (TweenTarget::Progress(from), TweenTarget::Progress(to)) => TweenTarget::Progress(from + (to - from) * t),
+            _ => *to,
+        }
+    }
+
+    fn apply(&self, properties: &mut Properties) {
+        match self {
+            TweenTarget::Alpha(alpha) => if let Some(color) = properties.color.as_deref_mut() {
+                color.set_alpha(*alpha);
+            },
+            TweenTarget::Tint(tint) => if let Some(color) = properties.color.as_deref_mut() {
+                *color = tint.with_alpha(color.alpha());
+            },
+            TweenTarget::Translation(translation) => if let Some(transform) = properties.transform.as_deref_mut() {
+                transform.translation = *translation;
+            },
+            TweenTarget::Progress(progress) => if let Some(current) = properties.progress.as_deref_mut() {
+                current.0 = *progress;
+            },
+        }
+    }
+
+    fn same_property(&self, other: &TweenTarget) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+// Everything on an entity a tween might animate
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct Animated {
+    transform: Option<&'static mut Transform>,
+    // Text and nodes are colored through whichever of these they have
+    sprite: Option<&'static mut Sprite>,
+    text_color: Option<&'static mut TextColor>,
+    background_color: Option<&'static mut BackgroundColor>,
+    progress: Option<&'static mut Progress>,
+}
+
+// What tweens can reach on one entity
+struct Properties<'a> {
+    transform: Option<&'a mut Transform>,
+    color: Option<&'a mut Color>,
+    progress: Option<&'a mut Progress>,
+}
+
+// One property animating from wherever it is when the tween starts
+#[derive(Debug, Clone)]
+pub struct Tween {
+    pub target: TweenTarget,
+    pub duration: f32,
+    // The script waits for it to finish
+    pub blocking: bool,
+    // The entity goes once it finishes, like a character fading out
+    pub despawn: bool,
+    from: Option<TweenTarget>,
+    elapsed: Stopwatch,
+}
+impl Tween {
+    pub fn new(target: TweenTarget, duration: f32) -> Self {
+        Self {
+            target,
+            duration,
+            blocking: false,
+            despawn: false,
+            from: None,
+            elapsed: Stopwatch::new(),
+        }
+    }
+
+    pub fn blocking(self) -> Self {
+        Self { blocking: true, ..self }
+    }
+
+    pub fn despawn_when_done(self) -> Self {
+        Self { despawn: true, ..self }
+    }
+
+    // Eased progress, from 0 when it starts to 1 when it's done
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0. {
+            return 1.;
+        }
+        ease(self.elapsed.elapsed_secs() / self.duration)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed.elapsed_secs() >= self.duration
+    }
+}
+
+/* Components */
+// How far along something drawn from a single value is, like a shader's effect
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress(pub f32);
+
+// Every property animating on an entity; starting a tween replaces any other on the same property
+#[derive(Component, Debug, Clone, Default)]
+pub struct Tweens(Vec<Tween>);
+impl Tweens {
+    pub fn push(&mut self, tween: Tween) {
+        self.0.retain(|existing| !existing.target.same_property(&tween.target));
+        self.0.push(tween);
+    }
+
+    // Where a property is headed, if it's animating
+    pub fn target(&self, property: &TweenTarget) -> Option<&TweenTarget> {
+        self.0.iter()
+            .map(|tween| &tween.target)
+            .find(|target| target.same_property(property))
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.0.iter().any(|tween| tween.blocking)
+    }
+}
+
+// Starts tweens from commands, on entities that may not have any yet
+pub trait TweenExt {
+    fn tween(&mut self, tween: Tween) -> &mut Self;
+}
+impl TweenExt for EntityCommands<'_> {
+    fn tween(&mut self, tween: Tween) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            match entity.get_mut::<Tweens>() {
+                Some(mut tweens) => tweens.push(tween),
+                None => {
+                    entity.insert(Tweens(vec![tween]));
+                },
+            }
+        })
+    }
+}
+
+pub struct TweenController;
+impl Plugin for TweenController {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, run_tweens.before(TransformSystems::Propagate));
+    }
+}
+
+// Moves every tween along by the frame's time. The script waits while any blocking
+//  tween runs, and goes on once the last one is done, replaced or despawned
+fn run_tweens(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut tween_query: Query<(Entity, &mut Tweens, Animated)>,
+    // Whether the script is waiting on tweens, as of last frame
+    mut holding: Local<bool>,
+) {
+    let mut still_blocking = false;
+    for (entity, mut tweens, mut animated) in tween_query.iter_mut() {
+        let color = animated.sprite.as_deref_mut().map(|sprite| &mut sprite.color)
+            .or(animated.text_color.as_deref_mut().map(|text_color| &mut text_color.0))
+            .or(animated.background_color.as_deref_mut().map(|background_color| &mut background_color.0));
+        let mut properties = Properties {
+            transform: animated.transform.as_deref_mut(),
+            color,
+            progress: animated.progress.as_deref_mut(),
+        };
+
+        let mut despawn = false;
+        for tween in tweens.0.iter_mut() {
+            if tween.from.is_none() {
+                tween.from = tween.target.current(&properties);
+            }
+            tween.elapsed.tick(time.delta());
+            // Lands exactly on the target, so it can be compared against
+            let value = match &tween.from {
+                Some(from) if !tween.is_finished() => from.lerp(&tween.target, tween.progress()),
+                _ => tween.target,
+            };
+            value.apply(&mut properties);
+            despawn |= tween.despawn && tween.is_finished();
+        }
+        tweens.0.retain(|tween| !tween.is_finished());
+
+        if despawn {
+            commands.entity(entity).despawn();
+        } else if tweens.0.is_empty() {
+            commands.entity(entity).remove::<Tweens>();
+        } else {
+            still_blocking |= tweens.is_blocking();
+        }
+    }
+
+    if still_blocking {
+        game_state.blocking = true;
+    } else if *holding {
+        game_state.blocking = false;
+    }
+    *holding = still_blocking;
+}
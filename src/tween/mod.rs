@@ -0,0 +1,9 @@
+pub mod controller;
+
+pub use controller::ease;
+pub use controller::Progress;
+pub use controller::Tween;
+pub use controller::TweenController;
+pub use controller::TweenExt;
+pub use controller::TweenTarget;
+pub use controller::Tweens;
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{compile_script, compile_statement};
+use sabi::audio::{AudioChannel, AudioFade, AudioVolumes};
+use sabi::compiler::ast::{Evaluate, Expr, StageCommand, StatementKind, Variables};
+
+use bevy::audio::Volume;
+use std::time::Duration;
+
+fn music(line: &str) -> (Option<String>, f32) {
+    match compile_statement(line).expect("Command should compile") {
+        StatementKind::Stage(StageCommand::MusicChange { music_expr, fade }) => {
+            let music_id = music_expr.map(|expr| expr.evaluate_into_string(&Variables::default()).expect("Music should evaluate"));
+            (music_id, fade)
+        },
+        other => panic!("Expected a music change, found {:?}", other),
+    }
+}
+
+#[test]
+fn music_plays_and_stops_with_optional_fades() {
+    assert_eq!(music("(Music \"theme\" plays)"), (Some(String::from("theme")), 0.));
+    assert_eq!(music("(Music \"theme\" plays over 2.5)"), (Some(String::from("theme")), 2.5));
+    assert_eq!(music("(Music stops)"), (None, 0.));
+    assert_eq!(music("(Music fades out)"), (None, 1.));
+    assert_eq!(music("(Music fades out over 3)"), (None, 3.));
+    assert!(compile_statement("(Music \"theme\" plays over -1)").is_err());
+}
+
+#[test]
+fn sounds_and_voices_take_expressions() {
+    let variables = Variables::default();
+    let StatementKind::Stage(StageCommand::SoundChange { sound_expr }) = compile_statement("(Sound \"door\" + \"_slam\" plays)").expect("Command should compile") else {
+        panic!("Expected a sound change");
+    };
+    assert_eq!(sound_expr.evaluate_into_string(&variables).expect("Sound should evaluate"), "door_slam");
+
+    let act = compile_script("SCENE main\n    Nayu: [\"hello\"] \"Hi!\"\n    Nayu: \"Quiet.\"\nCURTAIN\n").expect("Script should compile");
+    let voices: Vec<Option<String>> = act.scenes["main"].statements.iter()
+        .map(|statement| match &statement.kind {
+            StatementKind::Dialogue(dialogue) => dialogue.voice.as_ref()
+                .map(|voice| voice.evaluate_into_string(&variables).expect("Voice should evaluate")),
+            other => panic!("Expected dialogue, found {:?}", other),
+        })
+        .collect();
+    assert_eq!(voices, vec![Some(String::from("hello")), None]);
+    assert!(matches!(&act.scenes["main"].statements[0].kind, StatementKind::Dialogue(dialogue) if matches!(&dialogue.dialogue, Expr::String(line) if line == "Hi!")));
+}
+
+#[test]
+fn fades_move_gain_linearly_over_their_duration() {
+    let mut fade_in = AudioFade::new(0., 1., 2.);
+    assert_eq!(fade_in.tick(Duration::from_millis(500)), 0.25);
+    assert_eq!(fade_in.tick(Duration::from_millis(1000)), 0.75);
+    assert!(!fade_in.is_finished());
+    assert_eq!(fade_in.tick(Duration::from_millis(1000)), 1.);
+    assert!(fade_in.is_finished());
+
+    // Fading out starts from wherever the clip was
+    let mut fade_out = AudioFade::out(0.6, 1.);
+    assert!((fade_out.tick(Duration::from_millis(500)) - 0.3).abs() < 1e-6);
+
+    // No duration switches at once
+    let mut instant = AudioFade::new(0., 1., 0.);
+    assert_eq!(instant.tick(Duration::ZERO), 1.);
+}
+
+#[test]
+fn channel_volumes_scale_each_clip() {
+    let volumes = AudioVolumes { master: 0.5, music: 0.8, sound: 1., voice: 0.25 };
+    assert_eq!(volumes.volume(AudioChannel::Music, 0.5), Volume::Linear(0.2));
+    assert_eq!(volumes.volume(AudioChannel::Voice, 1.), Volume::Linear(0.125));
+}
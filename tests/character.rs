@@ -0,0 +1,29 @@
+use sabi::character::CharacterConfig;
+
+use bevy::prelude::*;
+
+#[test]
+fn shipped_character_configs_parse() {
+    let contents = std::fs::read_to_string("assets/characters/Nayu/character.json").expect("Shipped config should exist");
+    let config: CharacterConfig = serde_json::from_str(&contents).expect("Shipped config should parse");
+
+    assert_eq!(config.name, "Nayu");
+    assert!(config.emotions.contains(&config.emotion));
+    assert!(config.outfits.contains(&config.outfit));
+    assert_eq!(config.name_color, Some(Color::srgb_u8(0xf4, 0xa7, 0xc8)));
+}
+
+#[test]
+fn character_configs_read_their_chat_style() {
+    let config: CharacterConfig = serde_json::from_str(r##"{
+        "name": "Nayu", "outfit": "uniform", "emotion": "neutral", "description": "",
+        "emotions": ["neutral"], "outfits": ["uniform"],
+        "textbox": "TEXTBOX_NAYU", "name_color": "#ff000080", "font": "fonts/ALLER.ttf"
+    }"##).expect("Config should parse");
+
+    assert_eq!(config.textbox.as_deref(), Some("TEXTBOX_NAYU"));
+    assert_eq!(config.namebox, None);
+    assert_eq!(config.name_color, Some(Color::srgba_u8(255, 0, 0, 128)));
+    assert_eq!(config.text_color, None);
+    assert_eq!(config.font.as_deref(), Some("fonts/ALLER.ttf"));
+}
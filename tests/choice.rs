@@ -0,0 +1,63 @@
+mod common;
+
+use common::{compile_script, scratch_acts};
+use sabi::compiler::ast::{Evaluate, StageCommand, StatementKind, Variables};
+use sabi::headless::{HeadlessRunner, TranscriptEntry};
+
+const SCRIPT: &str = "\
+SCENE main
+    { choice \"Tea or coffee?\" }
+        { option \"Tea\" }
+            Nayu: \"Tea it is.\"
+            { set drink = \"tea\" }
+        { option \"Coffee\" -> \"cafe\" }
+    { end }
+    Nayu: \"One \" + drink + \", then.\"
+CURTAIN
+
+SCENE cafe
+    Nayu: \"Let's go out for coffee.\"
+CURTAIN
+";
+
+#[test]
+fn choices_compile_to_labelled_blocks() {
+    let act = compile_script(SCRIPT).expect("Script should compile");
+    let StatementKind::Choice { prompt, options } = &act.scenes["main"].statements[0].kind else {
+        panic!("Expected a choice");
+    };
+    let variables = Variables::default();
+    assert_eq!(prompt.as_ref().map(|prompt| prompt.evaluate_into_string(&variables).expect("Prompt should evaluate")).as_deref(), Some("Tea or coffee?"));
+
+    let labels: Vec<String> = options.iter()
+        .map(|option| option.label.evaluate_into_string(&variables).expect("Label should evaluate"))
+        .collect();
+    assert_eq!(labels, vec!["Tea", "Coffee"]);
+    assert_eq!(options[0].block.len(), 2);
+    // A target scene is a block that changes to it
+    assert!(matches!(&options[1].block[..], [statement] if matches!(statement.kind, StatementKind::Stage(StageCommand::SceneChange { .. }))));
+
+    assert!(compile_script("SCENE main\n    { choice }\n    { end }\nCURTAIN\n").is_err());
+}
+
+#[test]
+fn the_chosen_option_runs_its_block() {
+    let acts_path = scratch_acts("choice", SCRIPT);
+
+    let tea = HeadlessRunner::new(&acts_path)
+        .with_choices(vec![0])
+        .run()
+        .expect("Story should run to completion");
+    assert_eq!(tea.lines(), vec![("Nayu", "Tea it is."), ("Nayu", "One tea, then.")]);
+    assert!(tea.0.contains(&TranscriptEntry::Choice {
+        prompt: Some(String::from("Tea or coffee?")),
+        options: vec![String::from("Tea"), String::from("Coffee")],
+        chosen: 0,
+    }));
+
+    let coffee = HeadlessRunner::new(&acts_path)
+        .with_choices(vec![1])
+        .run()
+        .expect("Story should run to completion");
+    assert_eq!(coffee.lines(), vec![("Nayu", "Let's go out for coffee.")]);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::scratch_acts;
+
+use std::process::Command;
+
+fn sabi_cli() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_sabi-cli"))
+}
+
+#[test]
+fn check_passes_on_the_shipped_story() {
+    let output = sabi_cli().arg("check").output().expect("CLI should run");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn check_fails_on_missing_references() {
+    let acts_path = scratch_acts("cli-check", "SCENE main\n    (Scene \"mian\" begins)\n    Nayu: (hapy) \"Hi\"\nCURTAIN\n");
+    let output = sabi_cli().arg("check").arg("--acts").arg(&acts_path).output().expect("CLI should run");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("Scene 'mian' does not exist in act '1'"));
+    assert!(stderr.contains("Character 'Nayu' has no emotion 'hapy'"));
+    assert!(stderr.contains("Found 2 problem(s)"));
+}
+
+#[test]
+fn parse_errors_fail_every_command() {
+    let acts_path = scratch_acts("cli-parse", "SCENE main\n    (Background changes)\n");
+    let output = sabi_cli().arg("stats").arg("--acts").arg(&acts_path).output().expect("CLI should run");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn stats_count_words_per_character() {
+    let acts_path = scratch_acts("cli-stats", "SCENE main\n    MC: \"One two three\"\n    Nayu: \"Four five\" \"six\"\nCURTAIN\n");
+    let output = sabi_cli().arg("stats").arg("--acts").arg(&acts_path).output().expect("CLI should run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("Nayu       2 lines        3 words"), "{}", stdout);
+    assert!(stdout.contains("Total: 3 lines, 6 words"), "{}", stdout);
+}
+
+#[test]
+fn graph_follows_scene_changes() {
+    let output = sabi_cli().arg("graph").output().expect("CLI should run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("\"1/intro\" -> \"1/main\";"));
+    assert!(stdout.contains("\"1/epilogue\" -> \"2/beginning\";"));
+}
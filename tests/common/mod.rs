@@ -0,0 +1,43 @@
+// Not every test file uses every helper
+#![allow(dead_code)]
+
+use sabi::chat::typewriter::Typewriter;
+use sabi::compiler::ast::{compile_act, Act, StatementKind};
+use sabi::compiler::diagnostics::SourceFile;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub fn compile_script(text: &str) -> anyhow::Result<Act> {
+    let source = Arc::new(SourceFile {
+        path: "test.sabi".into(),
+        text: text.to_owned(),
+    });
+    compile_act(&source)
+}
+
+// The only statement of a `main` scene holding just `line`
+pub fn compile_statement(line: &str) -> anyhow::Result<StatementKind> {
+    let act = compile_script(&format!("SCENE main\n    {}\nCURTAIN\n", line))?;
+    Ok(act.scenes["main"].statements[0].kind.clone())
+}
+
+// A fresh folder for one test, named so parallel runs don't collide
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sabi-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&path).expect("Scratch folder should be creatable");
+    path
+}
+
+// An acts folder holding `script` as act 1
+pub fn scratch_acts(name: &str, script: &str) -> PathBuf {
+    let acts_path = scratch_dir(name);
+    std::fs::write(acts_path.join("1.sabi"), script).expect("Scratch script should be writable");
+    acts_path
+}
+
+// Types every grapheme after the same delay, with no pauses
+pub fn steady(character_delay: f32) -> Typewriter {
+    Typewriter { character_delay, punctuation_pauses: HashMap::new() }
+}
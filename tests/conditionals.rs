@@ -0,0 +1,116 @@
+mod common;
+
+use common::{compile_script, compile_statement};
+use sabi::compiler::ast::{CodeStatement, Evaluate, Expr, Statement, StatementKind, Variables};
+use sabi::compiler::execution::ExecutionStack;
+
+fn evaluate(expr: &str) -> anyhow::Result<Expr> {
+    let kind = compile_statement(&format!("{{ set result = {} }}", expr))?;
+    let StatementKind::Code(CodeStatement::Set { expr, .. }) = kind else {
+        panic!("Expected a set statement, found {:?}", kind);
+    };
+    let mut variables = Variables::default();
+    variables.set("count", Expr::Number(3.))?;
+    expr.evaluate(&variables)
+}
+
+fn said(statement: &Statement) -> Option<String> {
+    match &statement.kind {
+        StatementKind::Dialogue(dialogue) => Some(dialogue.dialogue.evaluate_into_string(&Variables::default()).expect("Line should evaluate")),
+        _ => None,
+    }
+}
+
+#[test]
+fn boolean_operators_bind_in_order() {
+    let check = |expr: &str, expected: bool| {
+        let value = evaluate(expr).expect("Expression should evaluate");
+        assert!(matches!(value, Expr::Boolean(b) if b == expected), "{} gave {:?}", expr, value);
+    };
+    // `and` binds tighter than `or`
+    check("true or false and false", true);
+    check("(true or false) and false", false);
+    // `not` binds looser than comparisons, but tighter than `and`
+    check("not count == 4", true);
+    check("not true and false", false);
+    check("not (true and false)", true);
+    // The right side isn't evaluated when the left decides
+    check("false and missing", false);
+    check("true or missing", true);
+    assert!(evaluate("true and missing").is_err());
+    assert!(evaluate("1 and true").is_err());
+}
+
+#[test]
+fn comparisons_work_on_numbers_strings_and_booleans() {
+    let check = |expr: &str, expected: bool| {
+        let value = evaluate(expr).expect("Expression should evaluate");
+        assert!(matches!(value, Expr::Boolean(b) if b == expected), "{} gave {:?}", expr, value);
+    };
+    check("count + 1 >= 4", true);
+    check("count < 3", false);
+    check("count <= 3", true);
+    check("count > 2 and count != 4", true);
+    check("\"abc\" == \"ab\" + \"c\"", true);
+    check("\"a\" < \"b\"", true);
+    check("true != false", true);
+    assert!(evaluate("count < \"3\"").is_err());
+}
+
+#[test]
+fn nested_blocks_are_entered_and_left_in_order() {
+    let act = compile_script("\
+SCENE main
+    MC: \"Before\"
+    { if true }
+        MC: \"Outer\"
+        { if false }
+            MC: \"Skipped\"
+        { else }
+            MC: \"Inner\"
+        { end }
+        MC: \"Outer again\"
+    { end }
+    MC: \"After\"
+CURTAIN
+").expect("Script should compile");
+
+    // Walks the scene taking the branch each condition picks, like the runner does
+    let mut stack = ExecutionStack::new(act.scenes["main"].statements.clone());
+    let mut lines = Vec::new();
+    while let Some(statement) = stack.next() {
+        if let StatementKind::If { cond, .. } = &statement.kind {
+            let taken = cond.evaluate_into_bool(&Variables::default()).expect("Condition should evaluate");
+            stack.enter(if taken { 0 } else { 1 }).expect("Branch should exist");
+            assert_eq!(stack.positions().last().map(|position| position.branch), Some(if taken { 0 } else { 1 }));
+        }
+        lines.extend(said(&statement));
+    }
+
+    assert_eq!(lines, vec!["Before", "Outer", "Inner", "Outer again", "After"]);
+    assert!(stack.is_finished());
+}
+
+#[test]
+fn else_if_chains_nest_inside_else() {
+    let act = compile_script("\
+SCENE main
+    { if count > 5 }
+        MC: \"Many\"
+    { else if count > 1 }
+        MC: \"Some\"
+    { else }
+        MC: \"Few\"
+    { end }
+CURTAIN
+").expect("Script should compile");
+
+    let StatementKind::If { then_block, else_block, .. } = &act.scenes["main"].statements[0].kind else {
+        panic!("Expected an if statement");
+    };
+    assert_eq!(then_block.len(), 1);
+    assert_eq!(else_block.len(), 1);
+    assert!(matches!(&else_block[0].kind, StatementKind::If { else_block, .. } if else_block.len() == 1));
+
+    assert!(compile_script("SCENE main\n    { if true }\n        MC: \"Unclosed\"\nCURTAIN\n").is_err());
+}
@@ -0,0 +1,56 @@
+use sabi::compiler::ast::compile_act;
+use sabi::compiler::diagnostics::SourceFile;
+
+use std::sync::Arc;
+
+fn source(text: &str) -> Arc<SourceFile> {
+    Arc::new(SourceFile {
+        path: "test.sabi".into(),
+        text: text.to_owned(),
+    })
+}
+
+#[test]
+fn statements_know_where_they_start() {
+    let act = compile_act(&source("SCENE main\n    MC: \"Hi!\"\n    (Scene \"main\" begins)\nCURTAIN\n"))
+        .expect("Script should compile");
+    let statements = &act.scenes["main"].statements;
+
+    assert_eq!((statements[0].span.line, statements[0].span.column), (2, 9));
+    assert_eq!((statements[1].span.line, statements[1].span.column), (3, 5));
+    assert_eq!(statements[1].span.to_string(), "test.sabi:3:5");
+}
+
+#[test]
+fn spans_render_with_a_caret() {
+    let act = compile_act(&source("SCENE main\n    (Background changes to \"x\")\nCURTAIN\n"))
+        .expect("Script should compile");
+    let span = &act.scenes["main"].statements[0].span;
+
+    assert_eq!(span.render("Background 'x' does not exist"), [
+        "Background 'x' does not exist",
+        " --> test.sabi:2:5",
+        "  |",
+        "2 |     (Background changes to \"x\")",
+        "  |     ^",
+    ].join("\n"));
+}
+
+#[test]
+fn build_errors_quote_the_script() {
+    let error = compile_act(&source("SCENE main\nCURTAIN\nSCENE main\nCURTAIN\n"))
+        .expect_err("Duplicate scenes should fail");
+    let report = format!("{:?}", error);
+
+    assert!(report.contains("Duplicate scene ID 'main'"));
+    assert!(report.contains("3 | SCENE main"));
+}
+
+#[test]
+fn parse_errors_name_the_file() {
+    let error = compile_act(&source("SCENE main\n    (Background changes)\nCURTAIN\n"))
+        .expect_err("Malformed stage command should fail");
+    let report = format!("{:?}", error);
+
+    assert!(report.contains("test.sabi:2:"));
+}
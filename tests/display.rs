@@ -0,0 +1,55 @@
+use sabi::chat::controller::sprite_contains;
+use sabi::chat::theme::ChatTheme;
+use sabi::display::{Letterbox, DESIGN_RESOLUTION};
+
+use bevy::prelude::*;
+
+#[test]
+fn letterbox_keeps_the_design_aspect_ratio() {
+    // Same size as the design fills the window
+    let exact = Letterbox::fit(DESIGN_RESOLUTION);
+    assert_eq!(exact, Letterbox { position: Vec2::ZERO, size: DESIGN_RESOLUTION });
+    assert_eq!(exact.scale(), 1.);
+
+    // Wider windows get bars on the sides
+    let wide = Letterbox::fit(Vec2::new(1920., 1080.));
+    assert_eq!(wide.size, Vec2::new(1728., 1080.));
+    assert_eq!(wide.position, Vec2::new(96., 0.));
+    assert_eq!(wide.scale(), 1.35);
+
+    // Taller windows get bars above and below
+    let tall = Letterbox::fit(Vec2::new(640., 800.));
+    assert_eq!(tall.size, Vec2::new(640., 400.));
+    assert_eq!(tall.position, Vec2::new(0., 200.));
+}
+
+#[test]
+fn window_positions_map_back_to_the_design() {
+    let letterbox = Letterbox::fit(Vec2::new(1920., 1080.));
+
+    assert_eq!(letterbox.to_design(Vec2::new(960., 540.)), Some(Vec2::ZERO));
+    assert_eq!(letterbox.to_design(Vec2::new(96., 0.)), Some(Vec2::new(-640., 400.)));
+    assert_eq!(letterbox.to_design(Vec2::new(1824., 1080.)), Some(Vec2::new(640., -400.)));
+    // Clicks on the bars hit nothing
+    assert_eq!(letterbox.to_design(Vec2::new(50., 540.)), None);
+    assert_eq!(letterbox.to_design(Vec2::new(1900., 540.)), None);
+}
+
+#[test]
+fn textbox_clicks_go_through_the_letterbox() {
+    let theme: ChatTheme = serde_json::from_str(&std::fs::read_to_string("assets/themes/default.theme.json").expect("Shipped theme should exist"))
+        .expect("Shipped theme should parse");
+    let element = &theme.elements["_textbox_background"];
+    let transform = GlobalTransform::from(Transform::from_translation(element.position(DESIGN_RESOLUTION)).with_scale(Vec3::splat(element.scale)));
+    let size = Vec2::new(796., 155.);
+
+    let letterbox = Letterbox::fit(Vec2::new(1920., 1080.));
+    let hits = |window_position: Vec2| letterbox.to_design(window_position)
+        .is_some_and(|point| sprite_contains(&transform, size, point));
+
+    // The textbox's center, scaled up with the rest of the view
+    assert!(hits(Vec2::new(960., 911.)));
+    // Level with it, but in the bar beside the view
+    assert!(!hits(Vec2::new(50., 911.)));
+    assert!(!hits(Vec2::new(960., 540.)));
+}
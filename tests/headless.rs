@@ -0,0 +1,52 @@
+use sabi::headless::{HeadlessRunner, TranscriptEntry};
+
+#[test]
+fn shipped_story_runs_to_completion() {
+    let transcript = HeadlessRunner::new("assets/acts")
+        .with_choices(vec![1])
+        .run()
+        .expect("Story should run to completion");
+
+    assert!(transcript.0.contains(&TranscriptEntry::Background(String::from("main_classroom_noon"))));
+    assert!(transcript.0.contains(&TranscriptEntry::Act(String::from("2"))));
+    assert!(transcript.lines().contains(&("Nayu", "And we can branch on them!")));
+    assert_eq!(transcript.lines().last(), Some(&("Nayu", "You could jump here with a scene change command.")));
+}
+
+#[test]
+fn choices_follow_the_scripted_answers() {
+    let stay = ("Nayu", "Choices can run their own lines before moving on.");
+
+    let skipped = HeadlessRunner::new("assets/acts")
+        .with_act("2")
+        .with_choices(vec![0])
+        .run()
+        .expect("Act 2 should run to completion");
+    assert!(!skipped.lines().contains(&stay));
+    assert!(skipped.0.contains(&TranscriptEntry::Scene(String::from("end"))));
+
+    let stayed = HeadlessRunner::new("assets/acts")
+        .with_act("2")
+        .with_choices(vec![1])
+        .run()
+        .expect("Act 2 should run to completion");
+    assert!(stayed.lines().contains(&stay));
+}
+
+#[test]
+fn missing_choices_are_reported() {
+    let result = HeadlessRunner::new("assets/acts")
+        .with_act("2")
+        .run();
+    assert!(result.is_err());
+}
+
+#[test]
+fn stories_that_never_start_fail_instead_of_hanging() {
+    let error = HeadlessRunner::new("assets/acts")
+        .with_max_frames(1)
+        .app()
+        .expect_err("The acts can't load in a single frame")
+        .to_string();
+    assert!(error.starts_with("Story did not start within 1 frames"), "{}", error);
+}
@@ -0,0 +1,33 @@
+use sabi::chat::history::{History, HistoryEntry, HISTORY_LIMIT};
+use sabi::save::controller::SaveData;
+
+fn entry(index: usize) -> HistoryEntry {
+    HistoryEntry {
+        name: String::from("Nayu"),
+        message: format!("Line {}", index),
+        voice: index.is_multiple_of(2).then(|| format!("nayu_{}", index)),
+    }
+}
+
+#[test]
+fn oldest_lines_are_dropped_past_the_limit() {
+    let mut history = History::default();
+    for index in 0..HISTORY_LIMIT + 5 {
+        history.push(entry(index));
+    }
+
+    assert_eq!(history.entries().len(), HISTORY_LIMIT);
+    assert_eq!(history.entries().front(), Some(&entry(5)));
+    assert_eq!(history.entries().back(), Some(&entry(HISTORY_LIMIT + 4)));
+}
+
+#[test]
+fn history_round_trips_through_save_data() {
+    let mut save_data = SaveData::default();
+    save_data.history.push(entry(0));
+    save_data.history.push(entry(1));
+
+    let json = serde_json::to_value(&save_data).expect("Save data should serialize");
+    let loaded: SaveData = serde_json::from_value(json).expect("Save data should deserialize");
+    assert_eq!(loaded.history, save_data.history);
+}
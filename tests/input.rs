@@ -0,0 +1,31 @@
+mod common;
+
+use common::scratch_dir;
+use sabi::input::{Action, Binding, InputMap, WheelDirection};
+use sabi::settings::read_settings;
+
+use bevy::prelude::*;
+
+#[test]
+fn shipped_settings_match_the_defaults() {
+    let input_map: InputMap = read_settings("settings/input.json".as_ref()).expect("Shipped settings should parse");
+    assert_eq!(input_map, InputMap::default());
+}
+
+#[test]
+fn settings_only_override_the_actions_they_list() {
+    let path = scratch_dir("input").join("input.json");
+    std::fs::write(&path, r#"{ "Advance": [{ "Key": "KeyZ" }, { "Wheel": "Down" }] }"#)
+        .expect("Scratch settings should be writable");
+
+    let input_map: InputMap = read_settings(&path).expect("Settings should parse");
+    assert_eq!(input_map.0[&Action::Advance], vec![Binding::Key(KeyCode::KeyZ), Binding::Wheel(WheelDirection::Down)]);
+    assert_eq!(input_map.0[&Action::Quicksave], InputMap::default().0[&Action::Quicksave]);
+}
+
+#[test]
+fn missing_settings_use_the_defaults() {
+    let path = scratch_dir("input").join("missing.json");
+    let input_map: InputMap = read_settings(&path).expect("Missing settings are fine");
+    assert_eq!(input_map, InputMap::default());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::steady;
+use sabi::chat::markup::{Pace, RichText, SpanStyle, StyledSpan, TextEffect};
+
+use bevy::prelude::*;
+
+fn span(text: &str, style: SpanStyle) -> StyledSpan {
+    StyledSpan { text: text.to_owned(), style }
+}
+
+#[test]
+fn tags_split_the_line_into_styled_spans() {
+    let rich = RichText::parse("Say {b}hello{/b} to {color=#ff0000}{wave}Nayu{/wave}{/color}, {ruby=かんじ}漢字{/ruby} {{ok}").expect("Markup should parse");
+
+    assert_eq!(rich.spans, vec![
+        span("Say ", SpanStyle::default()),
+        span("hello", SpanStyle { bold: true, ..default() }),
+        span(" to ", SpanStyle::default()),
+        span("Nayu", SpanStyle { color: Some(Color::srgb(1., 0., 0.)), effect: Some(TextEffect::Wave), ..default() }),
+        span(", ", SpanStyle::default()),
+        span("漢字", SpanStyle { ruby: Some(String::from("かんじ")), ..default() }),
+        span(" {ok}", SpanStyle::default()),
+    ]);
+    assert_eq!(rich.plain(), "Say hello to Nayu, 漢字 {ok}");
+    assert_eq!(rich.grapheme_count(), 26);
+}
+
+#[test]
+fn pauses_and_speed_changes_pace_the_typewriter() {
+    let rich = RichText::parse("ab{w=1}cd{speed=2}ef{/speed}g").expect("Markup should parse");
+    assert_eq!(rich.pacing, vec![(2, Pace::Wait(1.)), (4, Pace::Speed(Some(2.))), (6, Pace::Speed(None))]);
+
+    // 10 characters a second, with a second's wait before 'c' and 'e' to 'f' at 2
+    let times = rich.reveal_times(&steady(0.1));
+    let expected = [0.1, 0.2, 1.3, 1.4, 1.9, 2.4, 2.5];
+    assert_eq!(times.len(), expected.len());
+    for (time, expected) in times.iter().zip(expected) {
+        assert!((time - expected).abs() < 1e-4, "{:?}", times);
+    }
+}
+
+#[test]
+fn malformed_markup_is_an_error() {
+    for (markup, expected) in [
+        ("{b}bold{/i}", "'{/i}' closes '{b}', which is still open"),
+        ("plain{/b}", "'{/b}' closes a tag that was never opened"),
+        ("{blink}hi", "Unknown tag '{blink}'"),
+        ("{w=soon}", "'soon' in '{w=soon}' should be a positive number"),
+        ("{speed=0}", "'0' in '{speed=0}' should be a positive number"),
+        ("{color=#zz}", "Invalid color '#zz' in '{color=#zz}'"),
+        ("{ruby=x}{b}a{/b}{/ruby}", "Ruby text can't contain other tags, found '{b}'"),
+        ("{ruby=x}{/ruby}", "Ruby needs text to sit over"),
+        ("{b", "Tag '{b' is never closed with '}'"),
+    ] {
+        let error = RichText::parse(markup).expect_err(markup);
+        assert_eq!(error.to_string(), expected, "for {}", markup);
+    }
+    // Tags left open close with the line
+    assert!(RichText::parse("{i}trailing").is_ok());
+}
+
+// A typewriter without punctuation pauses
+#[test]
+fn graphemes_type_out_whole() {
+    // A family emoji, an accented letter made of two code points, and Japanese
+    let rich = RichText::parse("👨‍👩‍👧e\u{301}{w=1}日本").expect("Markup should parse");
+    assert_eq!(rich.grapheme_count(), 4);
+    assert_eq!(rich.pacing, vec![(2, Pace::Wait(1.))]);
+    assert_eq!(rich.reveal_times(&steady(1.)), vec![1., 2., 4., 5.]);
+}
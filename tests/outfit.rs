@@ -0,0 +1,48 @@
+mod common;
+
+use common::compile_statement;
+use sabi::character::{CharacterConfig, CharacterOperation};
+use sabi::character::controller::{CharacterSprites, SpriteKey};
+use sabi::compiler::ast::{StageCommand, StatementKind};
+
+use bevy::prelude::*;
+
+fn compile_command(command: &str) -> CharacterOperation {
+    match compile_statement(command).expect("Command should compile") {
+        StatementKind::Stage(StageCommand::CharacterChange { operation, .. }) => operation,
+        other => panic!("Expected a character change, found {:?}", other),
+    }
+}
+
+#[test]
+fn outfit_changes_may_crossfade() {
+    assert_eq!(compile_command("(Nayu wears casual)"), CharacterOperation::OutfitChange(String::from("casual"), 0.));
+    assert_eq!(compile_command("(Nayu wears summer_2 over 0.75)"), CharacterOperation::OutfitChange(String::from("summer_2"), 0.75));
+}
+
+#[test]
+fn missing_emotions_fall_back_within_the_outfit() {
+    let config = CharacterConfig {
+        name: String::from("Nayu"),
+        outfit: String::from("uniform"),
+        emotion: String::from("crying"),
+        emotions: vec![String::from("crying"), String::from("neutral"), String::from("happy")],
+        outfits: vec![String::from("uniform"), String::from("casual")],
+        ..Default::default()
+    };
+    let sprite = |outfit: &str, emotion: &str| (SpriteKey {
+        character: String::from("Nayu"),
+        outfit: outfit.to_owned(),
+        emotion: emotion.to_owned(),
+    }, Handle::<Image>::default());
+    let sprites = CharacterSprites::from([
+        sprite("uniform", "crying"),
+        sprite("uniform", "neutral"),
+        sprite("casual", "neutral"),
+        sprite("casual", "happy"),
+    ]);
+
+    assert_eq!(config.emotion_in_outfit("uniform", &sprites).as_deref(), Some("crying"));
+    assert_eq!(config.emotion_in_outfit("casual", &sprites).as_deref(), Some("neutral"));
+    assert_eq!(config.emotion_in_outfit("swimsuit", &sprites), None);
+}
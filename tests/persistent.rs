@@ -0,0 +1,86 @@
+mod common;
+
+use common::{compile_script, scratch_acts, scratch_dir};
+use sabi::compiler::ast::{Expr, StatementKind};
+use sabi::compiler::execution::{FramePosition, ProgramCounter};
+use sabi::headless::HeadlessRunner;
+use sabi::save::persistent::{line_id, write_persistent, PersistentData};
+use sabi::settings::read_settings;
+
+fn dialogue_ids(text: &str) -> Vec<String> {
+    let act = compile_script(text).expect("Script should compile");
+    act.scenes["main"].statements.iter()
+        .enumerate()
+        .filter_map(|(index, statement)| match &statement.kind {
+            StatementKind::Dialogue(dialogue) => {
+                // Where execution is while the line runs
+                let program_counter = ProgramCounter {
+                    act: String::from("1"),
+                    scene: String::from("main"),
+                    frames: vec![FramePosition { branch: 0, index: index + 1 }],
+                };
+                Some(line_id(&program_counter, dialogue))
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn line_ids_survive_unrelated_edits() {
+    let original = dialogue_ids("SCENE main\n    MC: \"Hello\"\n    Nayu: \"Hi\"\nCURTAIN\n");
+    let edited = dialogue_ids("SCENE main\n\n    MC: \"Hello\"\n\n    Nayu: \"Hi there\"\nCURTAIN\n");
+
+    assert_ne!(original[0], original[1]);
+    assert_eq!(original[0], edited[0]);
+    assert_ne!(original[1], edited[1]);
+}
+
+#[test]
+fn repeated_lines_have_their_own_ids() {
+    let ids = dialogue_ids("SCENE main\n    Nayu: \"...\"\n    MC: \"Hello?\"\n    Nayu: \"...\"\nCURTAIN\n");
+    assert_ne!(ids[0], ids[2]);
+}
+
+#[test]
+fn persistent_data_round_trips_through_its_file() {
+    let path = scratch_dir("persistent-file").join("persistent.json");
+    let _ = std::fs::remove_file(&path);
+    assert!(read_settings::<PersistentData>(&path).expect("Missing file is a first playthrough").seen_lines.is_empty());
+
+    let mut data = PersistentData::default();
+    assert!(data.mark_seen(String::from("1/main/0")));
+    assert!(!data.mark_seen(String::from("1/main/0")));
+    data.set_unlocked("beach", true);
+    data.set_cleared("good", true);
+    data.set_flag("met_nayu", Expr::Boolean(true)).expect("Flag should be settable");
+    data.set_skip_unread(true);
+    write_persistent(&path, &data).expect("Persistent data should be writable");
+
+    let loaded = read_settings::<PersistentData>(&path).expect("Persistent data should be readable");
+    assert!(loaded.has_seen("1/main/0"));
+    assert!(loaded.gallery.contains("beach"));
+    assert!(loaded.endings.contains("good"));
+    assert!(matches!(loaded.flags.get("met_nayu"), Some(Expr::Boolean(true))));
+    assert!(loaded.skip_unread);
+}
+
+#[test]
+fn scripts_read_and_set_global_flags() {
+    let acts_path = scratch_acts("persistent-script", "\
+SCENE main
+    MC: \"First time: \" + (not global.finished)
+    { set global.finished = true }
+    { set gallery.beach = true }
+    { set ending.good = true }
+    { if global.finished and gallery.beach and ending.good and not ending.bad }
+        MC: \"All set\"
+    { end }
+CURTAIN
+");
+
+    let transcript = HeadlessRunner::new(&acts_path)
+        .run()
+        .expect("Story should run to completion");
+    assert_eq!(transcript.lines(), vec![("MC", "First time: true"), ("MC", "All set")]);
+}
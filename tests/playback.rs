@@ -0,0 +1,90 @@
+mod common;
+
+use common::{scratch_acts, steady};
+use sabi::chat::playback::{advance_playback, auto_delay};
+use sabi::chat::{ChatAdvanceMessage, MarkupText, PlaybackMode, RichText};
+use sabi::compiler::execution::{FramePosition, ProgramCounter};
+use sabi::headless::HeadlessRunner;
+use sabi::{Object, VisualNovelState};
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+const SCRIPT: &str = "SCENE main\n    Nayu: \"Hello!\"\nCURTAIN\n";
+const FRAME: Duration = Duration::from_millis(100);
+
+#[derive(Resource, Default)]
+struct Advances(usize);
+
+fn count_advances(mut advance_message: MessageReader<ChatAdvanceMessage>, mut advances: ResMut<Advances>) {
+    advances.0 += advance_message.read().count();
+}
+
+// The story with playback on top, and a fully typed out line in the textbox
+fn playback_app(name: &str) -> App {
+    let acts_path = scratch_acts(name, SCRIPT);
+    let mut app = HeadlessRunner::new(acts_path).app().expect("Story should start");
+    app.add_message::<ChatAdvanceMessage>()
+        .init_resource::<PlaybackMode>()
+        .init_resource::<Advances>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .add_systems(Update, (advance_playback, count_advances).chain());
+
+    let mut markup = MarkupText::default();
+    markup.set(RichText::parse("Hello!").expect("Markup should parse"), &steady(0.));
+    markup.reveal(f32::MAX);
+    app.world_mut().spawn(markup);
+    app.world_mut().spawn((Object::new("_textbox_background"), Visibility::Visible));
+    app
+}
+
+fn advances(app: &App) -> usize {
+    app.world().resource::<Advances>().0
+}
+
+#[test]
+fn auto_mode_advances_once_the_delay_is_up() {
+    let mut app = playback_app("playback-auto");
+    app.world_mut().resource_mut::<PlaybackMode>().auto = true;
+
+    let delay = auto_delay("Hello!".len());
+    let frames = (delay / FRAME.as_secs_f32()).ceil() as usize;
+    for _ in 0..frames - 1 {
+        app.update();
+    }
+    assert_eq!(advances(&app), 0);
+
+    for _ in 0..2 {
+        app.update();
+    }
+    assert_eq!(advances(&app), 1);
+}
+
+#[test]
+fn skip_mode_stops_at_unread_lines() {
+    let mut app = playback_app("playback-skip");
+    let start = ProgramCounter {
+        act: String::from("1"),
+        scene: String::from("main"),
+        frames: vec![FramePosition { branch: 0, index: 0 }],
+    };
+
+    // The first time through, the line hasn't been read
+    app.update();
+    app.world_mut().resource_mut::<PlaybackMode>().skip = true;
+    app.update();
+    assert_eq!(advances(&app), 0);
+    assert!(!app.world().resource::<PlaybackMode>().skip);
+
+    // Once it has, skipping goes straight past it
+    app.world_mut().resource_mut::<VisualNovelState>()
+        .restore_program_counter(&start)
+        .expect("Start of the scene should exist");
+    app.update();
+    app.world_mut().resource_mut::<PlaybackMode>().skip = true;
+    app.update();
+    assert_eq!(advances(&app), 1);
+    assert!(app.world().resource::<PlaybackMode>().skip);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::{compile_statement, scratch_acts};
+use sabi::compiler::ast::{Evaluate, Expr, StageCommand, StatementKind, Variables};
+use sabi::headless::HeadlessRunner;
+use sabi::player::{validate_player_name, DEFAULT_PLAYER_NAME};
+
+fn compile_line(line: &str) -> anyhow::Result<Expr> {
+    match compile_statement(&format!("MC: {}", line))? {
+        StatementKind::Dialogue(dialogue) => Ok(dialogue.dialogue),
+        other => panic!("Expected dialogue, found {:?}", other),
+    }
+}
+
+#[test]
+fn only_shown_text_interpolates() {
+    let background = compile_statement("(Background changes to \"bg_[night]\")").expect("Command should compile");
+    match background {
+        StatementKind::Stage(StageCommand::BackgroundChange { background_expr, .. }) => {
+            assert!(matches!(*background_expr, Expr::String(id) if id == "bg_[night]"));
+        },
+        other => panic!("Expected a background change, found {:?}", other),
+    }
+
+    let choice = compile_statement("{ choice \"[player.name]?\" }\n        { option \"I'm [player.name]\" -> \"end\" }\n    { end }").expect("Choice should compile");
+    match choice {
+        StatementKind::Choice { prompt, options } => {
+            assert!(matches!(prompt, Some(Expr::Interpolation { .. })));
+            assert!(matches!(options[0].label, Expr::Interpolation { .. }));
+        },
+        other => panic!("Expected a choice, found {:?}", other),
+    }
+}
+
+#[test]
+fn the_old_player_name_placeholder_says_what_replaced_it() {
+    let error = compile_line("\"Hi, [_PLAYERNAME_]!\"").expect_err("Old placeholder should not compile");
+    assert!(format!("{:?}", error).contains("write '[player.name]'"), "{:?}", error);
+}
+
+#[test]
+fn player_names_are_trimmed_and_checked() {
+    assert_eq!(validate_player_name("  Bolt ").expect("Name should be valid"), "Bolt");
+    assert_eq!(validate_player_name("ナユ").expect("Non-ASCII names should be valid"), "ナユ");
+    assert_eq!(validate_player_name("👩‍👩‍👧").expect("One grapheme is one character"), "👩‍👩‍👧");
+    assert!(validate_player_name("   ").is_err());
+    assert!(validate_player_name("abcdefghijklmnopq").is_err());
+    assert!(validate_player_name("{b}Bolt").is_err());
+    assert!(validate_player_name("Bo\u{7}lt").is_err());
+}
+
+#[test]
+fn strings_interpolate_bracketed_expressions() {
+    let expr = compile_line("\"[count + 1] apples and [[brackets]\"").expect("Line should compile");
+    assert!(matches!(expr, Expr::Interpolation { .. }));
+
+    let mut variables = Variables::default();
+    variables.set("count", Expr::Number(2.)).expect("Variable should be settable");
+    assert_eq!(expr.evaluate_into_string(&variables).expect("Line should evaluate"), "3 apples and [brackets]");
+
+    let plain = compile_line("\"No brackets [[here]\"").expect("Line should compile");
+    assert!(matches!(plain, Expr::String(text) if text == "No brackets [here]"));
+
+    assert!(compile_line("\"Unclosed [count\"").is_err());
+    assert!(compile_line("\"Broken [count +]\"").is_err());
+}
+
+#[test]
+fn scripts_read_and_set_the_player_name() {
+    let acts_path = scratch_acts("player", "\
+SCENE main
+    Nayu: \"Hi, [player.name]!\"
+    { set player.name = \"Bolt\" }
+    Nayu: \"Oh, [player.name].\"
+CURTAIN
+");
+
+    let transcript = HeadlessRunner::new(&acts_path)
+        .run()
+        .expect("Story should run to completion");
+    let greeting = format!("Hi, {}!", DEFAULT_PLAYER_NAME);
+    assert_eq!(transcript.lines(), vec![("Nayu", greeting.as_str()), ("Nayu", "Oh, Bolt.")]);
+}
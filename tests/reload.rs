@@ -0,0 +1,72 @@
+mod common;
+
+use common::{compile_script, scratch_acts};
+use sabi::compiler::controller::CompilerConfig;
+use sabi::compiler::execution::{ExecutionStack, FramePosition};
+use sabi::compiler::reload::{acts_source, ActScript, ActScriptLoader, ACTS_SOURCE};
+use sabi::headless::HeadlessRunner;
+
+use std::path::Path;
+
+use bevy::asset::AssetPlugin;
+use bevy::prelude::*;
+
+const BROKEN_SCRIPT: &str = "SCENE main\n    (Background changes)\nCURTAIN\n";
+
+fn load_script(acts_path: &Path, script_path: &str) -> Result<(), String> {
+    let mut app = App::new();
+    app.register_asset_source(ACTS_SOURCE, acts_source(acts_path))
+        .insert_resource(CompilerConfig {
+            acts_path: acts_path.to_owned(),
+            entrypoint: None,
+        })
+        .add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<ActScript>()
+        .init_asset_loader::<ActScriptLoader>();
+
+    let handle: Handle<ActScript> = app.world().resource::<AssetServer>().load(format!("{}://{}", ACTS_SOURCE, script_path));
+    for _ in 0..1000 {
+        app.update();
+        if let Some(script) = app.world().resource::<Assets<ActScript>>().get(&handle) {
+            return script.act.as_ref().map(|_| ()).map_err(|error| error.clone());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    panic!("Script {} never loaded", script_path);
+}
+
+#[test]
+fn acts_load_through_the_asset_server() {
+    assert_eq!(load_script(Path::new("assets/acts"), "1.sabi"), Ok(()));
+}
+
+#[test]
+fn broken_acts_still_load_with_their_error() {
+    let acts_path = scratch_acts("reload", BROKEN_SCRIPT);
+
+    let error = load_script(&acts_path, "1.sabi")
+        .expect_err("Broken script should carry its error");
+    // Named by where it is on disk, not inside the asset source
+    assert!(error.contains(&format!("{}:2:", acts_path.join("1.sabi").display())), "{}", error);
+}
+
+#[test]
+fn broken_acts_are_reported_instead_of_starting() {
+    let acts_path = scratch_acts("reload-startup", BROKEN_SCRIPT);
+
+    let error = HeadlessRunner::new(&acts_path)
+        .run()
+        .expect_err("Story should not start with a broken act")
+        .to_string();
+    assert!(error.starts_with("Act '1' failed to compile"), "{}", error);
+    assert!(error.contains("1.sabi:2:"), "{}", error);
+}
+
+#[test]
+fn positions_past_the_end_of_an_edited_scene_are_rejected() {
+    let act = compile_script("SCENE main\n    MC: \"One\" \"Two\"\nCURTAIN\n").expect("Script should compile");
+    let statements = act.scenes["main"].statements.clone();
+
+    assert!(ExecutionStack::restore(statements.clone(), &[FramePosition { branch: 0, index: 2 }]).is_ok());
+    assert!(ExecutionStack::restore(statements, &[FramePosition { branch: 0, index: 3 }]).is_err());
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::scratch_acts;
+use sabi::audio::{AudioOperation, CurrentMusic};
+use sabi::background::Background;
+use sabi::character::{CharacterConfig, CharacterOperation, Configs, StagePlacement, StageSlot};
+use sabi::chat::GUIScrollText;
+use sabi::compiler::ast::{Expr, Scope};
+use sabi::compiler::controller::{SabiState, ScriptSystems};
+use sabi::headless::{HeadlessRunner, Transcript, TranscriptEntry};
+use sabi::save::controller::{handle_loads, handle_saves, slot_path, LoadGameMessage, SaveData, SaveGameMessage};
+use sabi::{Object, VisualNovelState};
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+const SCRIPT: &str = "\
+SCENE main
+    Nayu: \"Before\"
+    { choice }
+        { option \"Stay\" }
+            { if true }
+                Nayu: \"Inside\"
+                Nayu: \"Still inside\"
+            { end }
+            Nayu: \"Leaving the option\"
+    { end }
+    Nayu: \"After\"
+CURTAIN
+";
+
+fn said(app: &App) -> Vec<String> {
+    app.world().resource::<Transcript>().lines().iter()
+        .map(|(_, message)| message.to_string())
+        .collect()
+}
+
+fn run_to_end(app: &mut App) {
+    for _ in 0..1000 {
+        app.update();
+        let running = *app.world().resource::<State<SabiState>>().get() == SabiState::Running;
+        if running && app.world().resource::<VisualNovelState>().is_finished() {
+            return;
+        }
+    }
+    panic!("Story did not finish");
+}
+
+#[test]
+fn program_counters_inside_nested_blocks_survive_a_save() {
+    let acts_path = scratch_acts("save", SCRIPT);
+    let mut app = HeadlessRunner::new(&acts_path)
+        .with_choices(vec![0])
+        .app()
+        .expect("Story should start");
+
+    // Stop right after the first line inside the `if`, within the option's block
+    for _ in 0..1000 {
+        if said(&app).last().is_some_and(|line| line == "Inside") {
+            break;
+        }
+        app.update();
+    }
+    let save_data = SaveData {
+        program_counter: app.world().resource::<VisualNovelState>().program_counter(),
+        ..Default::default()
+    };
+    assert_eq!(save_data.program_counter.frames.len(), 3);
+    let saved = serde_json::to_string(&save_data).expect("Save should serialize");
+
+    run_to_end(&mut app);
+    let before_load = said(&app);
+    assert_eq!(before_load, vec!["Before", "Inside", "Still inside", "Leaving the option", "After"]);
+
+    let loaded: SaveData = serde_json::from_str(&saved).expect("Save should deserialize");
+    assert_eq!(loaded.program_counter, save_data.program_counter);
+    app.world_mut().resource_mut::<VisualNovelState>()
+        .restore_program_counter(&loaded.program_counter)
+        .expect("Saved position should exist");
+    app.world_mut().resource_mut::<Transcript>().0.clear();
+
+    run_to_end(&mut app);
+    assert_eq!(said(&app), before_load[2..]);
+}
+
+const STAGE_SCRIPT: &str = "\
+SCENE main
+    { set mood = \"calm\" }
+    Nayu: \"Before\"
+    { set mood = \"tense\" }
+    Nayu: \"After\"
+CURTAIN
+";
+// Out of the way of the quicksave slot and any real saves
+const TEST_SLOT: usize = 90;
+
+fn mood(app: &App) -> Option<Expr> {
+    app.world().resource::<VisualNovelState>().lookup("mood")
+}
+
+#[test]
+fn saves_restore_the_stage_and_variables() {
+    let acts_path = scratch_acts("save-stage", STAGE_SCRIPT);
+    let mut app = HeadlessRunner::new(&acts_path).app().expect("Story should start");
+    let nayu = CharacterConfig {
+        name: String::from("Nayu"),
+        outfit: String::from("school"),
+        emotion: String::from("happy"),
+        ..default()
+    };
+    app.add_message::<SaveGameMessage>()
+        .add_message::<LoadGameMessage>()
+        .insert_resource(CurrentMusic(Some(String::from("theme"))))
+        .insert_resource(Configs(HashMap::from([(nayu.name.clone(), nayu.clone())])))
+        // Before the script moves on in the same frame
+        .add_systems(Update, (handle_saves, handle_loads).chain().before(ScriptSystems));
+
+    // What the real controllers would have put on screen
+    let world = app.world_mut();
+    world.spawn(Background {
+        background_sprites: HashMap::new(),
+        mask_sprites: HashMap::new(),
+        current: Some(String::from("classroom")),
+    });
+    world.spawn((nayu, StagePlacement { slot: Some(StageSlot::Left), order: 1 }, Sprite::default()));
+    world.spawn((Object::new("_textbox_background"), Visibility::Visible));
+    world.spawn((Object::new("_name_text"), Text2d::new("Nayu"), GUIScrollText { message: String::new() }));
+    world.spawn((Object::new("_message_text"), Text2d::new("Before"), GUIScrollText { message: String::from("Before") }));
+
+    for _ in 0..1000 {
+        if said(&app).last().is_some_and(|line| line == "Before") {
+            break;
+        }
+        app.update();
+    }
+    app.world_mut().write_message(SaveGameMessage { slot: TEST_SLOT });
+    app.update();
+
+    let contents = std::fs::read_to_string(slot_path(TEST_SLOT)).expect("Save should be on disk");
+    let saved: SaveData = serde_json::from_str(&contents).expect("Save should parse");
+    assert_eq!(saved.background.as_deref(), Some("classroom"));
+    assert_eq!(saved.music.as_deref(), Some("theme"));
+    let cast: Vec<_> = saved.characters.iter()
+        .map(|character| (character.name.as_str(), character.outfit.as_str(), character.emotion.as_str(), character.position))
+        .collect();
+    assert_eq!(cast, vec![("Nayu", "school", "happy", Some(StageSlot::Left))]);
+    assert_eq!(saved.textbox.as_ref().map(|textbox| (textbox.name.as_str(), textbox.message.as_str())), Some(("Nayu", "Before")));
+    assert!(saved.choice.is_none());
+    assert!(matches!(saved.variables.get("mood"), Some(Expr::String(mood)) if mood == "calm"));
+
+    run_to_end(&mut app);
+    assert!(matches!(mood(&app), Some(Expr::String(mood)) if mood == "tense"));
+    app.world_mut().resource_mut::<Transcript>().0.clear();
+
+    app.world_mut().write_message(LoadGameMessage { slot: TEST_SLOT });
+    app.update();
+    std::fs::remove_file(slot_path(TEST_SLOT)).expect("Test save should be removable");
+    assert!(matches!(mood(&app), Some(Expr::String(mood)) if mood == "calm"));
+    let restored = app.world().resource::<Transcript>().0.clone();
+    assert!(restored.contains(&TranscriptEntry::Background(String::from("classroom"))));
+    assert!(restored.contains(&TranscriptEntry::Audio(AudioOperation::PlayMusic(String::from("theme"), 0.))));
+    assert!(restored.contains(&TranscriptEntry::Character {
+        character: String::from("Nayu"),
+        operation: CharacterOperation::Spawn(Some(String::from("happy")), Some(StageSlot::Left), 0.),
+    }));
+    assert!(restored.contains(&TranscriptEntry::Say { name: String::from("Nayu"), message: String::from("Before") }));
+
+    // And the story picks up after the saved line
+    run_to_end(&mut app);
+    assert_eq!(said(&app), vec!["Before", "After"]);
+}
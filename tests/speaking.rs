@@ -0,0 +1,33 @@
+mod common;
+
+use common::scratch_dir;
+use sabi::character::speaking::SpeakingStyle;
+use sabi::settings::read_settings;
+
+#[test]
+fn shipped_stage_settings_match_the_defaults() {
+    let style: SpeakingStyle = read_settings("settings/stage.json".as_ref()).expect("Shipped settings should parse");
+    assert_eq!(style, SpeakingStyle::default());
+}
+
+#[test]
+fn mouths_flap_while_lines_type_out() {
+    let style = SpeakingStyle { mouth_flap: 0.1, ..Default::default() };
+    assert!(style.mouth_open(0.));
+    assert!(style.mouth_open(0.05));
+    assert!(!style.mouth_open(0.15));
+    assert!(style.mouth_open(0.25));
+
+    let still = SpeakingStyle { mouth_flap: 0., ..Default::default() };
+    assert!(still.mouth_open(0.15));
+}
+
+#[test]
+fn stage_settings_only_override_what_they_list() {
+    let path = scratch_dir("speaking").join("stage.json");
+    std::fs::write(&path, r#"{ "listener_brightness": 1.0 }"#).expect("Scratch settings should be writable");
+
+    let style: SpeakingStyle = read_settings(&path).expect("Settings should parse");
+    assert_eq!(style.listener_brightness, 1.);
+    assert_eq!(style.mouth_flap, SpeakingStyle::default().mouth_flap);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::compile_statement;
+use sabi::character::{CharacterOperation, StagePlacement, StageSlot};
+use sabi::character::stage::stage_layout;
+use sabi::compiler::ast::{StageCommand, StatementKind};
+use sabi::display::DESIGN_RESOLUTION;
+
+fn compile_command(command: &str) -> anyhow::Result<CharacterOperation> {
+    match compile_statement(command)? {
+        StatementKind::Stage(StageCommand::CharacterChange { operation, .. }) => Ok(operation),
+        other => panic!("Expected a character change, found {:?}", other),
+    }
+}
+
+#[test]
+fn character_commands_take_positions() {
+    assert_eq!(compile_command("(Nayu appears left happy)").expect("Command should compile"),
+        CharacterOperation::Spawn(Some(String::from("happy")), Some(StageSlot::Left), 0.));
+    assert_eq!(compile_command("(Nayu fade in 0.4 over 0.5)").expect("Command should compile"),
+        CharacterOperation::Spawn(None, Some(StageSlot::At(0.4)), 0.5));
+    // Emotions that merely start like a slot are still emotions
+    assert_eq!(compile_command("(Nayu appears lefty)").expect("Command should compile"),
+        CharacterOperation::Spawn(Some(String::from("lefty")), None, 0.));
+    assert_eq!(compile_command("(Nayu moves to right over 1.5)").expect("Command should compile"),
+        CharacterOperation::Move(StageSlot::Right, 1.5));
+    assert!(matches!(compile_command("(Nayu moves to center)").expect("Command should compile"),
+        CharacterOperation::Move(StageSlot::Center, _)));
+
+    assert!(compile_command("(Nayu moves to 1.5)").is_err());
+    assert!(compile_command("(Nayu disappears left)").is_err());
+}
+
+fn assert_near(actual: Vec<f32>, expected: Vec<f32>) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual_x, expected_x) in actual.iter().zip(&expected) {
+        assert!((actual_x - expected_x).abs() < 1e-3, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn unslotted_characters_share_the_stage() {
+    let width = DESIGN_RESOLUTION.x;
+    let alone = stage_layout(&[StagePlacement { slot: None, order: 1 }]);
+    assert_near(alone, vec![0.]);
+
+    // Spread in the order they arrived, whatever order they're listed in
+    let pair = stage_layout(&[
+        StagePlacement { slot: None, order: 5 },
+        StagePlacement { slot: None, order: 2 },
+    ]);
+    assert_near(pair, vec![width / 6., -width / 6.]);
+
+    let mixed = stage_layout(&[
+        StagePlacement { slot: Some(StageSlot::Right), order: 1 },
+        StagePlacement { slot: None, order: 2 },
+        StagePlacement { slot: Some(StageSlot::At(0.)), order: 3 },
+    ]);
+    assert_near(mixed, vec![width / 4., 0., -width / 2.]);
+}
@@ -0,0 +1,48 @@
+use sabi::chat::theme::{ChatTheme, ChatThemeLoader, DEFAULT_THEME};
+
+use bevy::asset::{AssetPlugin, LoadState};
+use bevy::prelude::*;
+
+#[test]
+fn shipped_theme_loads_through_the_asset_server() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<ChatTheme>()
+        .init_asset_loader::<ChatThemeLoader>();
+
+    let handle: Handle<ChatTheme> = app.world().resource::<AssetServer>().load(DEFAULT_THEME);
+    for _ in 0..1000 {
+        app.update();
+        if let Some(theme) = app.world().resource::<Assets<ChatTheme>>().get(&handle) {
+            for id in ["_textbox_background", "_namebox_background", "_name_text", "_message_text", "_info_text"] {
+                assert!(theme.elements.contains_key(id), "Theme is missing '{}'", id);
+            }
+            return;
+        }
+        if let Some(LoadState::Failed(error)) = app.world().resource::<AssetServer>().get_load_state(&handle) {
+            panic!("Theme failed to load: {}", error);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    panic!("Theme never loaded");
+}
+
+#[test]
+fn elements_are_placed_relative_to_the_window() {
+    let theme: ChatTheme = serde_json::from_str(r##"{ "elements": {
+        "box": { "window_anchor": [0.5, 0.0], "offset": [0, 125], "z": 2 },
+        "text": { "color": "#ff000080" }
+    } }"##).expect("Theme should parse");
+
+    assert_eq!(theme.elements["box"].position(Vec2::new(1280., 800.)), Vec3::new(0., -275., 2.));
+    assert_eq!(theme.elements["box"].position(Vec2::new(1920., 1080.)), Vec3::new(0., -415., 2.));
+    // Anchors default to the window's center
+    assert_eq!(theme.elements["text"].position(Vec2::new(1280., 800.)), Vec3::ZERO);
+    assert_eq!(theme.elements["text"].color, Some(Color::srgba_u8(255, 0, 0, 128)));
+}
+
+#[test]
+fn bad_colors_fail_the_whole_theme() {
+    let result = serde_json::from_str::<ChatTheme>(r#"{ "elements": { "text": { "color": "reddish" } } }"#);
+    assert!(result.is_err());
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{compile_statement, scratch_acts, scratch_dir};
+use sabi::background::controller::folder_missing;
+use sabi::background::{BackgroundTransition, TransitionEffect, WipeDirection};
+use sabi::compiler::ast::{StageCommand, StatementKind};
+use sabi::headless::{HeadlessRunner, TranscriptEntry};
+
+use bevy::asset::{AssetPlugin, LoadState};
+use bevy::prelude::*;
+
+fn compile_command(command: &str) -> anyhow::Result<Option<BackgroundTransition>> {
+    match compile_statement(command)? {
+        StatementKind::Stage(StageCommand::BackgroundChange { transition, .. }) => Ok(transition),
+        other => panic!("Expected a background change, found {:?}", other),
+    }
+}
+
+fn transition(effect: TransitionEffect, duration: f32) -> Option<BackgroundTransition> {
+    Some(BackgroundTransition { effect, duration })
+}
+
+#[test]
+fn background_changes_take_a_transition() {
+    let compile = |command: &str| compile_command(command).expect("Command should compile");
+    assert_eq!(compile("(Background changes to \"night\")"), None);
+    assert_eq!(compile("(Background changes to \"night\" with dissolve 1.5)"), transition(TransitionEffect::Dissolve(None), 1.5));
+    assert_eq!(compile("(Background changes to \"night\" with dissolve)"), transition(TransitionEffect::Dissolve(None), 1.));
+    assert_eq!(compile("(Background changes to \"night\" with dissolve circle 0.5)"),
+        transition(TransitionEffect::Dissolve(Some(String::from("circle"))), 0.5));
+    assert_eq!(compile("(Background changes to \"night\" with wipe left 0.5)"), transition(TransitionEffect::Wipe(WipeDirection::Left), 0.5));
+    assert_eq!(compile("(Background changes to \"night\" with wipe down)"), transition(TransitionEffect::Wipe(WipeDirection::Down), 1.));
+    assert_eq!(compile("(Background changes to \"night\" with fade 2)"), transition(TransitionEffect::FadeThroughBlack, 2.));
+
+    assert!(compile_command("(Background changes to \"night\" with wipe sideways)").is_err());
+    assert!(compile_command("(Background changes to \"night\" with dissolve -1)").is_err());
+}
+
+#[test]
+fn stories_continue_after_transitions() {
+    let acts_path = scratch_acts("transition", "\
+SCENE main
+    (Background changes to \"night\" with fade 1.0)
+    Nayu: \"It got dark.\"
+    (Background changes to \"day\" with wipe right 0.5)
+    Nayu: \"And bright again.\"
+CURTAIN
+");
+
+    let transcript = HeadlessRunner::new(&acts_path)
+        .run()
+        .expect("Story should run to completion");
+    assert_eq!(transcript.lines(), vec![("Nayu", "It got dark."), ("Nayu", "And bright again.")]);
+    assert!(transcript.0.contains(&TranscriptEntry::Background(String::from("day"))));
+}
+
+#[test]
+fn missing_mask_folders_are_told_apart_from_broken_ones() {
+    let assets_path = scratch_dir("transition-assets");
+    std::fs::create_dir_all(assets_path.join("empty")).expect("Scratch folder should be creatable");
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin {
+        file_path: assets_path.to_string_lossy().into_owned(),
+        ..default()
+    }));
+
+    let asset_server = app.world().resource::<AssetServer>().clone();
+    let missing = asset_server.load_folder("transitions");
+    let empty = asset_server.load_folder("empty");
+    let settled = |state: &Option<LoadState>| matches!(state, Some(LoadState::Loaded | LoadState::Failed(_)));
+    for _ in 0..1000 {
+        app.update();
+        if settled(&asset_server.get_load_state(missing.id())) && settled(&asset_server.get_load_state(empty.id())) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    assert!(asset_server.get_load_state(missing.id()).is_some_and(|state| folder_missing(&state)));
+    assert!(asset_server.get_load_state(empty.id()).is_some_and(|state| state.is_loaded() && !folder_missing(&state)));
+}
@@ -0,0 +1,118 @@
+use sabi::tween::{ease, Progress, Tween, TweenController, TweenTarget, Tweens};
+use sabi::VisualNovelState;
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use std::time::Duration;
+
+fn tween_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TweenController))
+        .init_resource::<VisualNovelState>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)));
+    // Time starts counting from the first frame
+    app.update();
+    app
+}
+
+#[test]
+fn easing_starts_and_ends_in_place() {
+    assert_eq!(ease(0.), 0.);
+    assert_eq!(ease(1.), 1.);
+    assert_eq!(ease(2.), 1.);
+    assert!(ease(0.25) < 0.25);
+    assert!(ease(0.75) > 0.75);
+}
+
+#[test]
+fn tweens_follow_time_rather_than_frames() {
+    let mut app = tween_app();
+    let mut tweens = Tweens::default();
+    tweens.push(Tween::new(TweenTarget::Translation(Vec3::new(100., 0., 0.)), 0.4));
+    tweens.push(Tween::new(TweenTarget::Alpha(0.), 0.4));
+    let entity = app.world_mut().spawn((Sprite::default(), Transform::default(), tweens)).id();
+
+    app.update();
+    app.update();
+    // Halfway through, where easing lands halfway too
+    let transform = app.world().get::<Transform>(entity).expect("Entity should still exist");
+    assert!((transform.translation.x - 50.).abs() < 1e-3);
+    let sprite = app.world().get::<Sprite>(entity).expect("Entity should still exist");
+    assert!((sprite.color.alpha() - 0.5).abs() < 1e-3);
+
+    app.update();
+    app.update();
+    assert_eq!(app.world().get::<Transform>(entity).map(|transform| transform.translation.x), Some(100.));
+    assert!(app.world().get::<Tweens>(entity).is_none());
+}
+
+#[test]
+fn newer_tweens_replace_older_ones_on_the_same_property() {
+    let mut tweens = Tweens::default();
+    tweens.push(Tween::new(TweenTarget::Alpha(0.), 1.));
+    tweens.push(Tween::new(TweenTarget::Translation(Vec3::ONE), 1.));
+    tweens.push(Tween::new(TweenTarget::Alpha(1.), 1.));
+    assert_eq!(tweens.target(&TweenTarget::Alpha(0.5)), Some(&TweenTarget::Alpha(1.)));
+    assert_eq!(tweens.target(&TweenTarget::Translation(Vec3::ZERO)), Some(&TweenTarget::Translation(Vec3::ONE)));
+    assert_eq!(tweens.target(&TweenTarget::Tint(Color::WHITE)), None);
+}
+
+#[test]
+fn finished_tweens_can_despawn_their_entity() {
+    let mut app = tween_app();
+    let mut tweens = Tweens::default();
+    tweens.push(Tween::new(TweenTarget::Alpha(0.), 0.2).despawn_when_done());
+    let entity = app.world_mut().spawn((Sprite::default(), tweens)).id();
+
+    app.update();
+    assert!(app.world().get_entity(entity).is_ok());
+    app.update();
+    app.update();
+    assert!(app.world().get_entity(entity).is_err());
+}
+
+#[test]
+fn gui_colors_and_progress_tween_too() {
+    let mut app = tween_app();
+    let mut text_tweens = Tweens::default();
+    text_tweens.push(Tween::new(TweenTarget::Alpha(0.), 0.2));
+    let text = app.world_mut().spawn((Text::new("Hi"), TextColor(Color::WHITE), text_tweens)).id();
+    let mut node_tweens = Tweens::default();
+    node_tweens.push(Tween::new(TweenTarget::Tint(Color::BLACK), 0.2));
+    let node = app.world_mut().spawn((Node::default(), BackgroundColor(Color::WHITE.with_alpha(0.5)), node_tweens)).id();
+    let mut progress_tweens = Tweens::default();
+    progress_tweens.push(Tween::new(TweenTarget::Progress(1.), 0.2));
+    let progress = app.world_mut().spawn((Progress::default(), progress_tweens)).id();
+
+    app.update();
+    app.update();
+    assert_eq!(app.world().get::<TextColor>(text).map(|color| color.0.alpha()), Some(0.));
+    // Tinting leaves the alpha alone
+    assert_eq!(app.world().get::<BackgroundColor>(node).map(|color| color.0), Some(Color::BLACK.with_alpha(0.5)));
+    assert_eq!(app.world().get::<Progress>(progress), Some(&Progress(1.)));
+}
+
+fn script_waiting(app: &App) -> bool {
+    !app.world().resource::<VisualNovelState>().is_finished()
+}
+
+#[test]
+fn the_script_waits_until_blocking_tweens_are_gone() {
+    let mut app = tween_app();
+    let mut tweens = Tweens::default();
+    tweens.push(Tween::new(TweenTarget::Alpha(0.), 1.).blocking());
+    let replaced = app.world_mut().spawn((Sprite::default(), tweens.clone())).id();
+    let despawned = app.world_mut().spawn((Sprite::default(), tweens)).id();
+
+    app.update();
+    assert!(script_waiting(&app));
+
+    // Neither a tween taking over the property nor a despawn leaves it waiting
+    app.world_mut().get_mut::<Tweens>(replaced).expect("Entity should still be tweening")
+        .push(Tween::new(TweenTarget::Alpha(1.), 1.));
+    app.update();
+    assert!(script_waiting(&app));
+    app.world_mut().despawn(despawned);
+    app.update();
+    assert!(!script_waiting(&app));
+}
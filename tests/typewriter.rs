@@ -0,0 +1,58 @@
+mod common;
+
+use common::{scratch_dir, steady};
+use sabi::chat::markup::RichText;
+use sabi::chat::typewriter::Typewriter;
+use sabi::settings::read_settings;
+
+use bevy::prelude::*;
+
+#[test]
+fn sentences_pause_after_their_punctuation() {
+    let typewriter = Typewriter { character_delay: 1., ..default() };
+    let pause = typewriter.punctuation_pauses["."];
+    let ellipsis = typewriter.punctuation_pauses["…"];
+
+    let line = RichText::parse("Hi. Ok?! 3.1…はい。").expect("Markup should parse");
+    let times = line.reveal_times(&typewriter);
+    let steady_times = line.reveal_times(&steady(1.));
+    // After "Hi." and once after "?!", never inside "3.1", after the ellipsis, and not at the very end
+    let bang = typewriter.punctuation_pauses["!"];
+    let expected = [
+        0., 0., 0.,
+        pause, pause, pause, pause, pause,
+        pause + bang, pause + bang, pause + bang, pause + bang, pause + bang,
+        pause + bang + ellipsis, pause + bang + ellipsis, pause + bang + ellipsis,
+    ];
+    assert_eq!(times.len(), expected.len());
+    for ((time, steady), expected) in times.iter().zip(&steady_times).zip(expected) {
+        assert!((time - steady - expected).abs() < 1e-4, "{:?}", times);
+    }
+}
+
+#[test]
+fn abbreviations_only_pause_at_their_last_dot() {
+    let typewriter = Typewriter::default();
+    let pause = typewriter.punctuation_pauses["."];
+
+    // "e.g. so": the dot inside the word doesn't pause, the one before the space does
+    assert_eq!(typewriter.pause_between(".", "g"), 0.);
+    assert_eq!(typewriter.pause_between(".", " "), pause);
+    assert_eq!(typewriter.pause_between(".", "1"), 0.);
+}
+
+#[test]
+fn shipped_text_settings_match_the_defaults() {
+    let typewriter: Typewriter = read_settings("settings/text.json".as_ref()).expect("Shipped settings should parse");
+    assert_eq!(typewriter, Typewriter::default());
+}
+
+#[test]
+fn text_settings_only_override_what_they_list() {
+    let path = scratch_dir("typewriter").join("text.json");
+    std::fs::write(&path, r#"{ "character_delay": 0.05 }"#).expect("Scratch settings should be writable");
+
+    let typewriter: Typewriter = read_settings(&path).expect("Settings should parse");
+    assert_eq!(typewriter.character_delay, 0.05);
+    assert_eq!(typewriter.punctuation_pauses, Typewriter::default().punctuation_pauses);
+}
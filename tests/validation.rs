@@ -0,0 +1,141 @@
+mod common;
+
+use common::{compile_script, scratch_acts};
+use sabi::character::{CharacterConfig, CharactersConfig};
+use sabi::compiler::ast::Acts;
+use sabi::compiler::validation::{validate_acts, AssetCatalog};
+use sabi::headless::HeadlessRunner;
+
+use std::collections::HashSet;
+
+const SCRIPT: &str = r#"SCENE main
+    (Background changes to "classroom")
+    (Background changes to "clasroom")
+    (GUI textbox changes to "TEXTBOX")
+    Nayu: (hapy) "Hello!"
+    { if true }
+        (Scene "mian" begins)
+    { end }
+    (Act "3" begins)
+CURTAIN
+"#;
+
+fn compile(script: &str) -> Acts {
+    let act = compile_script(script).expect("Script should compile");
+
+    let mut acts = Acts::new();
+    acts.insert(String::from("1"), Box::new(act));
+    acts
+}
+
+fn catalog() -> AssetCatalog {
+    let mut characters = CharactersConfig::new();
+    characters.insert(String::from("Nayu"), CharacterConfig {
+        name: String::from("Nayu"),
+        outfit: String::from("uniform"),
+        emotion: String::from("neutral"),
+        emotions: vec![String::from("neutral"), String::from("happy")],
+        outfits: vec![String::from("uniform")],
+        ..Default::default()
+    });
+
+    AssetCatalog {
+        backgrounds: Some(HashSet::from([String::from("classroom")])),
+        transition_masks: Some(HashSet::from([String::from("circle")])),
+        gui_sprites: Some(HashSet::from([String::from("TEXTBOX")])),
+        characters: Some(characters),
+        character_sprites: None,
+    }
+}
+
+// Checks the rendered errors, in the order they're reported
+fn assert_reported(script: &str, catalog: &AssetCatalog, expected: &[&str]) {
+    let errors = validate_acts(&compile(script), catalog);
+    let reported: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+    assert_eq!(reported, expected);
+}
+
+#[test]
+fn every_problem_is_reported_with_its_line() {
+    assert_reported(SCRIPT, &catalog(), &[
+        "test.sabi:3:5: Background 'clasroom' does not exist",
+        "test.sabi:5:12: Character 'Nayu' has no emotion 'hapy'",
+        "test.sabi:7:9: Scene 'mian' does not exist in act '1'",
+        "test.sabi:9:5: Act '3' does not exist",
+    ]);
+}
+
+#[test]
+fn unloaded_assets_are_not_checked() {
+    let errors = validate_acts(&compile(SCRIPT), &AssetCatalog::default());
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn speaker_styles_are_checked_once_per_character() {
+    let script = "SCENE main\n    MC: \"Hi.\"\n    Nayu: \"Hello!\"\n    Nayu: \"Hello again!\"\nCURTAIN\n";
+    let mut catalog = catalog();
+    if let Some(characters) = &mut catalog.characters {
+        let nayu = characters.get_mut("Nayu").expect("Nayu should be in the catalog");
+        nayu.textbox = Some(String::from("TEXTBOX"));
+        nayu.namebox = Some(String::from("NAMEBOX_NAYU"));
+    }
+
+    assert_reported(script, &catalog, &[
+        "test.sabi:3:11: Character 'Nayu' uses GUI sprite 'NAMEBOX_NAYU', which does not exist",
+    ]);
+}
+
+#[test]
+fn markup_is_checked_in_constant_lines() {
+    let script = "SCENE main\n    MC: \"{b}Hi.{/i}\"\nCURTAIN\n";
+    assert_reported(script, &catalog(), &[
+        "test.sabi:2:9: Invalid markup: '{/i}' closes '{b}', which is still open",
+    ]);
+}
+
+#[test]
+fn outfit_changes_are_checked_against_the_wardrobe() {
+    let script = "SCENE main\n    (Nayu wears uniform)\n    (Nayu wears casual over 0.5)\n    (Nayu wears swimsuit)\nCURTAIN\n";
+    let mut catalog = catalog();
+    if let Some(characters) = &mut catalog.characters {
+        let nayu = characters.get_mut("Nayu").expect("Nayu should be in the catalog");
+        nayu.outfits.push(String::from("casual"));
+    }
+    catalog.character_sprites = Some(HashSet::from([
+        (String::from("Nayu"), String::from("uniform"), String::from("neutral")),
+    ]));
+
+    assert_reported(script, &catalog, &[
+        "test.sabi:3:5: Character 'Nayu' has no sprites for outfit 'casual'",
+        "test.sabi:4:5: Character 'Nayu' has no outfit 'swimsuit'",
+    ]);
+}
+
+#[test]
+fn transition_masks_are_checked() {
+    let script = "SCENE main\n    (Background changes to \"classroom\" with dissolve circle 0.5)\n    (Background changes to \"classroom\" with dissolve star)\nCURTAIN\n";
+    assert_reported(script, &catalog(), &[
+        "test.sabi:3:5: Transition mask 'star' does not exist",
+    ]);
+}
+
+#[test]
+fn speakers_need_a_character_config() {
+    let script = "SCENE main\n    MC: \"Hi.\"\n    Nayu: \"Hello!\"\n    Nayuu: \"Hello?\"\nCURTAIN\n";
+    assert_reported(script, &catalog(), &[
+        "test.sabi:4:12: Character 'Nayuu' does not exist",
+    ]);
+}
+
+#[test]
+fn problems_hold_the_story_instead_of_crashing() {
+    let acts_path = scratch_acts("validation", "SCENE main\n    (Scene \"nowhere\" begins)\nCURTAIN\n");
+    let error = HeadlessRunner::new(acts_path.clone())
+        .run()
+        .expect_err("A missing scene should stop the run")
+        .to_string();
+    std::fs::remove_dir_all(&acts_path).ok();
+
+    assert!(error.contains("Scene 'nowhere' does not exist in act '1'"), "{}", error);
+}